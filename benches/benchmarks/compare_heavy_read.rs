@@ -1,8 +1,9 @@
-use criterion::{criterion_group, BenchmarkId, Criterion, Fun, ParameterizedBenchmark};
+use criterion::{criterion_group, Criterion, Fun};
 
 use super::ListMutex;
 use super::ListOLock;
 
+#[allow(static_mut_refs, non_upper_case_globals)]
 fn heavy_read_list_mutex(i: i32) {
     static mut lock: Option<ListMutex> = None;
     unsafe { lock = Some(ListMutex::new()) };
@@ -39,6 +40,7 @@ fn heavy_read_list_mutex(i: i32) {
     let _ = thread5.join();
     unsafe { assert_eq!(lock.as_ref().unwrap().get2(), 2 * i) }
 }
+#[allow(static_mut_refs, non_upper_case_globals)]
 fn heavy_read_list_optimistic_lock_coupling(i: i32) {
     static mut lock: Option<ListOLock> = None;
     unsafe { lock = Some(ListOLock::new()) };
@@ -68,11 +70,11 @@ fn heavy_read_list_optimistic_lock_coupling(i: i32) {
     let thread3 = spawn(read_fn2);
     let thread4 = spawn(read_fn1);
     let thread5 = spawn(read_fn2);
-    let _1 = thread1.join().unwrap();
-    let _2 = thread2.join().unwrap();
-    let _3 = thread3.join().unwrap();
-    let _4 = thread4.join().unwrap();
-    let _5 = thread5.join().unwrap();
+    thread1.join().unwrap();
+    thread2.join().unwrap();
+    thread3.join().unwrap();
+    thread4.join().unwrap();
+    thread5.join().unwrap();
     unsafe { assert_eq!(lock.as_ref().unwrap().get2(), 2 * i) }
 }
 
@@ -1,8 +1,9 @@
-use criterion::{criterion_group, BenchmarkId, Criterion, Fun, ParameterizedBenchmark};
+use criterion::{criterion_group, Criterion, Fun};
 
 use super::ListMutex;
 use super::ListOLock;
 
+#[allow(static_mut_refs, non_upper_case_globals)]
 fn read_only_list_mutex(i: i32) {
     static mut lock: Option<ListMutex> = None;
     unsafe { lock = Some(ListMutex::new()) };
@@ -30,6 +31,7 @@ fn read_only_list_mutex(i: i32) {
     let _ = thread4.join();
     let _ = thread5.join();
 }
+#[allow(static_mut_refs, non_upper_case_globals)]
 fn read_only_list_optimistic_lock_coupling(i: i32) {
     static mut lock: Option<ListOLock> = None;
     unsafe { lock = Some(ListOLock::new()) };
@@ -51,11 +53,11 @@ fn read_only_list_optimistic_lock_coupling(i: i32) {
     let thread3 = spawn(read_fn2);
     let thread4 = spawn(read_fn1);
     let thread5 = spawn(read_fn2);
-    let _1 = thread1.join().unwrap();
-    let _2 = thread2.join().unwrap();
-    let _3 = thread3.join().unwrap();
-    let _4 = thread4.join().unwrap();
-    let _5 = thread5.join().unwrap();
+    thread1.join().unwrap();
+    thread2.join().unwrap();
+    thread3.join().unwrap();
+    thread4.join().unwrap();
+    thread5.join().unwrap();
 }
 
 fn lock_read_only_list(c: &mut Criterion) {
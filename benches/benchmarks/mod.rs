@@ -4,7 +4,7 @@ pub mod compare_heavy_write;
 pub mod compare_read_only;
 pub mod compare_write_only;
 
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{Arc, Mutex};
 
 use optimistic_lock_coupling::OptimisticLockCoupling;
 
@@ -41,8 +41,7 @@ impl ListMutex {
     // simutale select * from db where id = 2
     fn get2(&self) -> i32 {
         let t1 = self.tail.as_ref().unwrap().as_ref().lock().unwrap();
-        let h2 = t1.head;
-        h2
+        t1.head
     }
     // simulate update id = 2
     fn set2(&self) {
@@ -80,18 +79,14 @@ impl ListOLock {
     fn get_all(&self) -> (i32, i32, i32) {
         loop {
             let h1 = self.head;
-            if let Ok((h2, h3)) = self.tail.as_ref().unwrap().read_txn(
+            // nothing read is used before the guard it came from is validated
+            if let Ok((h2, h3)) = unsafe { self.tail.as_ref().unwrap().read() }.and_then(
                 #[inline(always)]
                 |rg| {
                     let h2 = rg.head;
-                    let h3 = rg.tail.as_ref().unwrap().read_txn(
-                        #[inline(always)]
-                        |rg| {
-                            let h3 = rg.head;
-                            Ok(h3)
-                        },
-                    )?;
-                    Ok((h2, h3))
+                    let rg = rg.couple(|n| n.tail.as_deref().unwrap())?;
+                    let h3 = rg.head;
+                    rg.try_sync().map(|_| (h2, h3))
                 },
             ) {
                 return (h1, h2, h3);
@@ -118,18 +113,16 @@ impl ListOLock {
     }
     // simulate update id = 2
     fn set2(&self) {
-        loop {
-            let t1 = self.tail.as_ref().unwrap().as_ref().write();
-            match t1 {
-                Ok(mut t1) => {
+        self.tail
+            .as_ref()
+            .unwrap()
+            .write_txn(
+                #[inline(always)]
+                |t1| {
                     t1.head += 1;
-                    break;
-                }
-                Err(_) => {
-                    continue;
-                }
-            }
-        }
+                },
+            )
+            .unwrap();
     }
     // simulate update id = 3
     fn set3(&self) {
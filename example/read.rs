@@ -4,7 +4,8 @@ use optimistic_lock_coupling::{OptimisticLockCoupling, OptimisticLockCouplingErr
 #[inline(always)]
 fn read_txn(lock: &OptimisticLockCoupling<i32>) -> Result<(), OptimisticLockCouplingErrorType> {
     // acquire the read lock
-    let read_guard = lock.read()?;
+    // nothing read through the guard is used after `try_sync`
+    let read_guard = unsafe { lock.read()? };
     // do your stuff
     println!("status: {}", read_guard);
     println!("\tmy operations: {} + 1 = {}", *read_guard, *read_guard + 1);
@@ -0,0 +1,167 @@
+//! Guards that own their lock through an `Arc`.
+//!
+//! Same validation and release as the borrowed guards, but with no lifetime,
+//! so they can live in a struct or be returned by the function that cloned the `Arc`.
+use alloc::sync::Arc;
+use core::{
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::Ordering::*,
+};
+
+use crate::{
+    panicking, version_of, DefaultLockWord, LockWord, OptimisticLockCoupling,
+    OptimisticLockCouplingErrorType, OptimisticLockCouplingResult,
+};
+
+/// `OptimisticLockCouplingReadGuard` holding an `Arc`, see `OptimisticLockCoupling::read_arc`
+pub struct OptimisticLockCouplingArcReadGuard<T: ?Sized, W: LockWord = DefaultLockWord> {
+    lock: Arc<OptimisticLockCoupling<T, W>>,
+    version: W,
+    /// raw pointers are not `Send`, so neither is the guard
+    _not_send: PhantomData<*const ()>,
+}
+/// the marker takes `Sync` away as well, give it back
+unsafe impl<T: ?Sized + Send + Sync, W: LockWord> Sync
+    for OptimisticLockCouplingArcReadGuard<T, W>
+{
+}
+#[cfg(feature = "nightly")]
+impl<T: ?Sized, W: LockWord> !Send for OptimisticLockCouplingArcReadGuard<T, W> {}
+impl<T: ?Sized, W: LockWord> OptimisticLockCouplingArcReadGuard<T, W> {
+    #[inline(always)]
+    pub(crate) fn new(
+        lock: Arc<OptimisticLockCoupling<T, W>>,
+    ) -> OptimisticLockCouplingResult<Self> {
+        let version = lock.try_lock()?;
+        Ok(Self {
+            lock,
+            version,
+            _not_send: PhantomData,
+        })
+    }
+    /// the lock this guard reads
+    #[inline(always)]
+    pub fn lock(guard: &Self) -> &Arc<OptimisticLockCoupling<T, W>> {
+        &guard.lock
+    }
+    /// Consume self and try to turn the read into a write
+    /// fails with `VersionUpdated` if any writer touched the data after the read
+    #[inline(always)]
+    pub fn upgrade(
+        self,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingArcWriteGuard<T, W>> {
+        use OptimisticLockCouplingErrorType::*;
+        if self.lock.is_poisoned() {
+            return Err(Poisoned);
+        }
+        if self.lock.lock_version(self.version) {
+            Ok(OptimisticLockCouplingArcWriteGuard { lock: self.lock })
+        } else {
+            Err(VersionUpdated)
+        }
+    }
+    /// Consume self return retry or not
+    #[inline(always)]
+    pub fn try_sync(self) -> OptimisticLockCouplingResult<()> {
+        self.lock.validate(self.version)
+    }
+    /// validate without giving the guard up
+    #[inline(always)]
+    pub fn check(&self) -> OptimisticLockCouplingResult<()> {
+        self.lock.validate(self.version)
+    }
+}
+impl<T: ?Sized, W: LockWord> Deref for OptimisticLockCouplingArcReadGuard<T, W> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+impl<T: Debug, W: LockWord> Debug for OptimisticLockCouplingArcReadGuard<T, W> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OptimisticLockCouplingArcReadGuard")
+            .field("version", &version_of(self.version))
+            .field("data", self.deref())
+            .finish()
+    }
+}
+
+/// `OptimisticLockCouplingWriteGuard` holding an `Arc`, see `OptimisticLockCoupling::write_arc`
+pub struct OptimisticLockCouplingArcWriteGuard<T: ?Sized, W: LockWord = DefaultLockWord> {
+    lock: Arc<OptimisticLockCoupling<T, W>>,
+}
+impl<T: ?Sized, W: LockWord> OptimisticLockCouplingArcWriteGuard<T, W> {
+    /// the caller must hold the lock
+    #[inline(always)]
+    pub(crate) fn new(lock: Arc<OptimisticLockCoupling<T, W>>) -> Self {
+        Self { lock }
+    }
+    /// the lock this guard holds
+    #[inline(always)]
+    pub fn lock(guard: &Self) -> &Arc<OptimisticLockCoupling<T, W>> {
+        &guard.lock
+    }
+    /// the lock, taken out without running `Drop`
+    #[inline(always)]
+    fn into_lock(self) -> Arc<OptimisticLockCoupling<T, W>> {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe { core::ptr::read(&this.lock) }
+    }
+    /// Consume self, release the lock and keep reading from the version you just wrote
+    ///
+    /// # Safety
+    /// the result is a raw read guard, same rules as `OptimisticLockCoupling::read`
+    #[inline(always)]
+    pub unsafe fn downgrade(self) -> OptimisticLockCouplingArcReadGuard<T, W> {
+        let lock = self.into_lock();
+        let version = lock.unlock(W::ZERO);
+        OptimisticLockCouplingArcReadGuard {
+            lock,
+            version,
+            _not_send: PhantomData,
+        }
+    }
+    /// Consume self, release the lock and mark the data outdated in one atomic step
+    #[inline(always)]
+    pub fn unlock_obsolete(self) {
+        let lock = self.into_lock();
+        let mark = if panicking() { W::POISON } else { W::ZERO };
+        lock.unlock(W::OUTDATE | mark);
+    }
+}
+impl<T: ?Sized, W: LockWord> Deref for OptimisticLockCouplingArcWriteGuard<T, W> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+impl<T: ?Sized, W: LockWord> DerefMut for OptimisticLockCouplingArcWriteGuard<T, W> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+impl<T: ?Sized, W: LockWord> Drop for OptimisticLockCouplingArcWriteGuard<T, W> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let mark = if panicking() { W::POISON } else { W::ZERO };
+        self.lock.unlock(mark);
+    }
+}
+impl<T: Debug, W: LockWord> Debug for OptimisticLockCouplingArcWriteGuard<T, W> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OptimisticLockCouplingArcWriteGuard")
+            .field(
+                "version",
+                &version_of(W::load(&self.lock.version_lock_outdate, Relaxed)),
+            )
+            .field("data", self.deref())
+            .finish()
+    }
+}
@@ -0,0 +1,142 @@
+//! Strategies for waiting between two optimistic attempts.
+//!
+//! A failed attempt (`Blocked` or `VersionUpdated`) means somebody else is writing,
+//! retrying at once only burns the core that writer may need.
+//! Pass one of these to `read_txn_with` / `write_txn_with`, or any other `_with` helper, to decide how to wait.
+use crate::sync::spin_loop;
+#[cfg(feature = "std")]
+use crate::sync::yield_now;
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+/// How a thread waits before redoing a failed attempt
+pub trait Backoff {
+    /// wait a bit, called every time an attempt has to be redone
+    fn backoff(&mut self);
+    /// forget the history, called when a new transaction starts
+    #[inline(always)]
+    fn reset(&mut self) {}
+}
+
+impl<B: Backoff + ?Sized> Backoff for &mut B {
+    #[inline(always)]
+    fn backoff(&mut self) {
+        (**self).backoff()
+    }
+    #[inline(always)]
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// only a `spin_loop` hint between retries
+/// good when the write sections are tiny
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpinBackoff;
+impl Backoff for SpinBackoff {
+    #[inline(always)]
+    fn backoff(&mut self) {
+        spin_loop();
+    }
+}
+
+/// give the rest of the time slice back to the scheduler
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YieldBackoff;
+#[cfg(feature = "std")]
+impl Backoff for YieldBackoff {
+    #[inline(always)]
+    fn backoff(&mut self) {
+        yield_now();
+    }
+}
+
+/// spins 1, 2, 4 ... 2^`spin_limit` times, after that yields on every retry
+/// without `std` there is nothing to yield to, it keeps spinning 2^`spin_limit` times
+/// this is the default of `read_txn` and `write_txn`
+#[derive(Debug, Clone, Copy)]
+pub struct ExponentialBackoff {
+    step: u32,
+    spin_limit: u32,
+}
+impl ExponentialBackoff {
+    /// spin up to 2^`spin_limit` times before starting to yield
+    #[inline(always)]
+    pub const fn new(spin_limit: u32) -> Self {
+        Self {
+            step: 0,
+            spin_limit,
+        }
+    }
+}
+impl Default for ExponentialBackoff {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(6)
+    }
+}
+impl Backoff for ExponentialBackoff {
+    #[inline(always)]
+    fn backoff(&mut self) {
+        if self.step <= self.spin_limit {
+            for _ in 0..1u32 << self.step {
+                spin_loop();
+            }
+            self.step += 1;
+        } else {
+            #[cfg(feature = "std")]
+            yield_now();
+            #[cfg(not(feature = "std"))]
+            for _ in 0..1u32 << self.spin_limit {
+                spin_loop();
+            }
+        }
+    }
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.step = 0;
+    }
+}
+
+/// sleeps, the sleep doubles from `min` up to `max`
+/// for long write sections where readers should really get off the CPU
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct ParkBackoff {
+    current: Duration,
+    min: Duration,
+    max: Duration,
+}
+#[cfg(feature = "std")]
+impl ParkBackoff {
+    /// start sleeping for `min`, never sleep longer than `max`
+    #[inline(always)]
+    pub const fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            current: min,
+            min,
+            max,
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl Default for ParkBackoff {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Duration::from_micros(1), Duration::from_millis(1))
+    }
+}
+#[cfg(feature = "std")]
+impl Backoff for ParkBackoff {
+    #[inline(always)]
+    fn backoff(&mut self) {
+        // a sleep, not a `park_timeout`, which would eat the unpark token of whoever parks this thread next
+        std::thread::sleep(self.current);
+        self.current = core::cmp::min(self.current * 2, self.max);
+    }
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.current = self.min;
+    }
+}
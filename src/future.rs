@@ -0,0 +1,86 @@
+//! Futures for async users, built on the same parking table as the blocking api.
+//!
+//! Nothing here depends on a runtime, a task waiting for a writer leaves its `Waker`
+//! and the writer wakes it when the guard is dropped.
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    parking, DefaultLockWord, LockWord, OptimisticLockCoupling, OptimisticLockCouplingErrorType,
+    OptimisticLockCouplingResult, OptimisticLockCouplingWriteGuard,
+};
+
+/// Future of `OptimisticLockCoupling::write_async`
+/// holds nothing but the reference until it resolves, so dropping it any time is fine
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct OptimisticLockCouplingWriteFuture<'a, T: ?Sized + 'a, W: LockWord = DefaultLockWord> {
+    lock: &'a OptimisticLockCoupling<T, W>,
+}
+impl<'a, T: ?Sized, W: LockWord> OptimisticLockCouplingWriteFuture<'a, T, W> {
+    #[inline(always)]
+    pub(crate) fn new(lock: &'a OptimisticLockCoupling<T, W>) -> Self {
+        Self { lock }
+    }
+}
+impl<'a, T: ?Sized, W: LockWord> Future for OptimisticLockCouplingWriteFuture<'a, T, W> {
+    type Output = OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'a, T, W>>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use OptimisticLockCouplingErrorType::*;
+        let lock = self.lock;
+        loop {
+            match lock.write() {
+                Err(Blocked) => {
+                    if parking::register(lock.addr(), cx.waker(), || lock.is_blocked()) {
+                        return Poll::Pending;
+                    }
+                }
+                // lost the race against another writer, try again
+                Err(VersionUpdated) => continue,
+                res => return Poll::Ready(res),
+            }
+        }
+    }
+}
+
+/// resolves once no alive writer holds the lock
+pub(crate) struct Unblocked<'a, T: ?Sized + 'a, W: LockWord> {
+    lock: &'a OptimisticLockCoupling<T, W>,
+}
+impl<'a, T: ?Sized, W: LockWord> Unblocked<'a, T, W> {
+    #[inline(always)]
+    pub(crate) fn new(lock: &'a OptimisticLockCoupling<T, W>) -> Self {
+        Self { lock }
+    }
+}
+impl<T: ?Sized, W: LockWord> Future for Unblocked<'_, T, W> {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let lock = self.lock;
+        if parking::register(lock.addr(), cx.waker(), || lock.is_blocked()) {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+/// gives the executor one chance to run something else
+#[derive(Default)]
+pub(crate) struct YieldNow {
+    yielded: bool,
+}
+impl Future for YieldNow {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
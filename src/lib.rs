@@ -1,6 +1,5 @@
-#![feature(negative_impls)]
-#![feature(unboxed_closures)]
-#![feature(stmt_expr_attributes)]
+#![no_std]
+#![cfg_attr(feature = "nightly", feature(negative_impls))]
 //! This crate provides a general optimistic lock.
 //!
 //! # Description
@@ -10,15 +9,41 @@
 //!
 //! This is the intermediate form. So this can be used everywhere as a general lock, and the performance is satisfactory.
 //!
+//! # Features
+//!
+//! - `std` (default): blocking, timed and async acquisition, poisoning when a writer panics.
+//!   Without it the crate is `no_std`, panics can't be detected so a panicking writer
+//!   releases its lock like any other and the lock is never poisoned.
+//! - `alloc` (enabled by `std`): `read_path_txn` and its `OlcPath`, `ReadSet`, `Transaction`, `Arc` guards.
+//! - `nightly`: explicit negative impls, needs a nightly toolchain.
+//!
 //! # Simple example for read
 //!
+//! the data is only reachable inside the closure, and nothing borrowed from it gets out
+//! ```
+//! use optimistic_lock_coupling::OptimisticLockCoupling;
+//!
+//! let lock = OptimisticLockCoupling::new(1);
+//! // one attempt, fails if a writer got in the way
+//! let plus_one = lock.read_with(|data| *data + 1);
+//! assert_eq!(plus_one, Ok(2));
+//! ```
+//! ```compile_fail
+//! use optimistic_lock_coupling::OptimisticLockCoupling;
+//!
+//! let lock = OptimisticLockCoupling::new(vec![1]);
+//! // references can't escape the validation
+//! let escaped: &Vec<i32> = lock.read_with(|data| data).unwrap();
+//! ```
+//! the raw guard is still there if you know what you are doing
 //! ```
 //! use optimistic_lock_coupling::{OptimisticLockCoupling, OptimisticLockCouplingErrorType};
 //!
 //! #[inline(always)]
 //! fn read_txn(lock: &OptimisticLockCoupling<i32>) -> Result<(), OptimisticLockCouplingErrorType> {
 //!     // acquire the read lock
-//!     let read_guard = lock.read()?;
+//!     // nothing read through the guard is used after `try_sync`
+//!     let read_guard = unsafe { lock.read()? };
 //!     // do your stuff
 //!     println!("status: {}", read_guard);
 //!     println!("\tmy operations: {} + 1 = {}", *read_guard, *read_guard + 1);
@@ -103,21 +128,66 @@
 //! unsafe { assert_eq!(*(LOCK.as_ref().unwrap().write().unwrap()), i) }
 //! ```
 
-use std::{
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+use core::{
     cell::UnsafeCell,
     fmt::Display,
+    marker::PhantomData,
+    mem::MaybeUninit,
     ops::{Deref, DerefMut},
-    sync::atomic::{AtomicBool, AtomicU64},
 };
-use std::{fmt::Debug, sync::atomic::Ordering::*};
+use core::{fmt::Debug, sync::atomic::Ordering::*};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+#[cfg(target_has_atomic = "64")]
+use sync::AtomicU64;
+use sync::{const_fn, fence, AtomicU32};
+
+mod sync;
+mod word;
+pub use word::{DefaultLockWord, LockWord};
+
+mod backoff;
+pub use backoff::*;
+#[cfg(feature = "alloc")]
+mod arc;
+#[cfg(feature = "alloc")]
+pub use arc::{OptimisticLockCouplingArcReadGuard, OptimisticLockCouplingArcWriteGuard};
+mod no_uninit;
+pub use no_uninit::NoUninit;
+#[cfg(feature = "alloc")]
+mod path;
+#[cfg(feature = "alloc")]
+pub use path::OlcPath;
+#[cfg(feature = "alloc")]
+mod read_set;
+#[cfg(feature = "alloc")]
+pub use read_set::ReadSet;
+#[cfg(feature = "alloc")]
+mod txn;
+#[cfg(feature = "alloc")]
+pub use txn::Transaction;
+#[cfg(feature = "std")]
+mod future;
+#[cfg(feature = "std")]
+mod parking;
+#[cfg(feature = "std")]
+pub use future::OptimisticLockCouplingWriteFuture;
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod test;
+#[cfg(all(test, loom))]
+mod test_loom;
 
 /// Error types
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum OptimisticLockCouplingErrorType {
-    /// writer thread panics without release the lock
+    /// writer thread panicked while holding the lock
+    /// see `try_write`, `write_ignore_poison` and `clear_poison` to recover
     Poisoned,
     /// writer thread set this data is outdated
     Outdated,
@@ -125,44 +195,216 @@ pub enum OptimisticLockCouplingErrorType {
     Blocked,
     /// reader thead try to sync after writer thread write things into lock
     VersionUpdated,
+    /// the deadline passed before the lock could be acquired
+    TimedOut,
 }
 /// Result type~
 pub type OptimisticLockCouplingResult<T> = Result<T, OptimisticLockCouplingErrorType>;
 
+/// A writer panicked, but here is the guard anyway
+/// same idea as `std::sync::PoisonError`
+pub struct OptimisticLockCouplingPoisonError<G> {
+    guard: G,
+}
+impl<G> OptimisticLockCouplingPoisonError<G> {
+    /// take the guard and repair the data yourself
+    #[inline(always)]
+    pub fn into_inner(self) -> G {
+        self.guard
+    }
+    #[inline(always)]
+    pub fn get_ref(&self) -> &G {
+        &self.guard
+    }
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+}
+impl<G> Debug for OptimisticLockCouplingPoisonError<G> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OptimisticLockCouplingPoisonError")
+            .finish_non_exhaustive()
+    }
+}
+impl<G> core::fmt::Display for OptimisticLockCouplingPoisonError<G> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("poisoned lock: a writer panicked while holding it")
+    }
+}
+#[cfg(feature = "std")]
+impl<G> std::error::Error for OptimisticLockCouplingPoisonError<G> {}
+
+/// Error of `try_write`
+pub enum OptimisticLockCouplingTryLockError<G> {
+    /// the lock is acquired but poisoned, the guard is inside
+    Poisoned(OptimisticLockCouplingPoisonError<G>),
+    /// the lock is not acquired
+    Failed(OptimisticLockCouplingErrorType),
+}
+/// no `G: Debug`, the guard is never printed, like `std::sync::TryLockError`
+impl<G> Debug for OptimisticLockCouplingTryLockError<G> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Poisoned(e) => f.debug_tuple("Poisoned").field(e).finish(),
+            Self::Failed(e) => f.debug_tuple("Failed").field(e).finish(),
+        }
+    }
+}
+impl<G> core::fmt::Display for OptimisticLockCouplingTryLockError<G> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Poisoned(e) => core::fmt::Display::fmt(e, f),
+            Self::Failed(e) => write!(f, "the lock could not be acquired: {:?}", e),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl<G> std::error::Error for OptimisticLockCouplingTryLockError<G> {}
+impl<G> From<OptimisticLockCouplingTryLockError<G>> for OptimisticLockCouplingErrorType {
+    #[inline(always)]
+    fn from(e: OptimisticLockCouplingTryLockError<G>) -> Self {
+        match e {
+            OptimisticLockCouplingTryLockError::Poisoned(_) => {
+                OptimisticLockCouplingErrorType::Poisoned
+            }
+            OptimisticLockCouplingTryLockError::Failed(e) => e,
+        }
+    }
+}
+
+/// What a lock does when its version runs out of bits
+///
+/// a wrapped version could make a reader validate against a write it never saw,
+/// so the version never wraps, the lock is left outdated instead
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum VersionOverflowPolicy {
+    /// mark the lock outdated, then panic in the writer that hit the limit
+    #[default]
+    Panic,
+    /// only mark the lock outdated, owners should replace the node like after `unlock_obsolete`
+    Outdate,
+}
+impl VersionOverflowPolicy {
+    #[inline(always)]
+    const fn bit<W: LockWord>(self) -> W {
+        match self {
+            VersionOverflowPolicy::Panic => W::ZERO,
+            VersionOverflowPolicy::Outdate => W::OUTDATE_ON_OVERFLOW,
+        }
+    }
+}
+
 /// Our data structure, the usage is 'pretty much' same as RwLock
-pub struct OptimisticLockCoupling<T: ?Sized> {
-    /// 60 bit for version | 1 bit for lock | 1 bit for outdate
-    version_lock_outdate: AtomicU64,
-    /// guard thread paniced
-    poisoned: AtomicBool,
+///
+/// `W` is the word the lock lives in, `u32` makes it smaller, see `LockWord`
+///
+/// `T` can be unsized, build it from a `Box<[T]>` or a `Vec<T>`, or coerce a boxed one
+/// ```
+/// use optimistic_lock_coupling::OptimisticLockCoupling;
+/// use std::fmt::Debug;
+///
+/// let slice: Box<OptimisticLockCoupling<[u64]>> = vec![1, 2, 3].into();
+/// assert_eq!(slice.read_with(|s| s.iter().sum::<u64>()), Ok(6));
+/// let debug: Box<OptimisticLockCoupling<dyn Debug>> = Box::new(OptimisticLockCoupling::new(1));
+/// assert_eq!(debug.read_with(|d| format!("{:?}", d)), Ok("1".to_string()));
+/// ```
+// `C` so the unsized construction knows where the data starts
+#[repr(C)]
+pub struct OptimisticLockCoupling<T: ?Sized, W: LockWord = DefaultLockWord> {
+    /// 1 bit for poison | 1 bit for overflow policy | version | 1 bit for lock | 1 bit for outdate
+    version_lock_outdate: W::Atomic,
     /// well the data
     data: UnsafeCell<T>,
 }
 
 /// Of course Lock could be Send
-unsafe impl<T: ?Sized + Send> Send for OptimisticLockCoupling<T> {}
+unsafe impl<T: ?Sized + Send, W: LockWord> Send for OptimisticLockCoupling<T, W> {}
 /// Of course Lock could be Sync
-unsafe impl<T: ?Sized + Send + Sync> Sync for OptimisticLockCoupling<T> {}
+unsafe impl<T: ?Sized + Send + Sync, W: LockWord> Sync for OptimisticLockCoupling<T, W> {}
 
-impl<T> OptimisticLockCoupling<T> {
-    /// create an instance of OLC
+#[cfg(target_has_atomic = "64")]
+impl<T> OptimisticLockCoupling<T, u64> {
+    const_fn! {
+        /// create an instance of OLC
+        #[inline(always)]
+        pub fn new(t: T) -> Self {
+            Self::with_overflow_policy(t, VersionOverflowPolicy::Panic)
+        }
+    }
+    const_fn! {
+        /// create an instance of OLC that does `policy` when its version runs out
+        #[inline(always)]
+        pub fn with_overflow_policy(t: T, policy: VersionOverflowPolicy) -> Self {
+            Self {
+                version_lock_outdate: AtomicU64::new(policy.bit()),
+                data: UnsafeCell::new(t),
+            }
+        }
+    }
+}
+/// the compact lock, half the size for 28 bits of version, see `LockWord`
+impl<T> OptimisticLockCoupling<T, u32> {
+    const_fn! {
+        /// create an instance of OLC on a 32 bit word
+        #[inline(always)]
+        pub fn new_compact(t: T) -> Self {
+            Self::compact_with_overflow_policy(t, VersionOverflowPolicy::Panic)
+        }
+    }
+    const_fn! {
+        /// create an instance of OLC on a 32 bit word that does `policy` when its version runs out
+        #[inline(always)]
+        pub fn compact_with_overflow_policy(t: T, policy: VersionOverflowPolicy) -> Self {
+            Self {
+                version_lock_outdate: AtomicU32::new(policy.bit()),
+                data: UnsafeCell::new(t),
+            }
+        }
+    }
+}
+impl<T, W: LockWord> OptimisticLockCoupling<T, W> {
+    /// take the data out, no one else can hold the lock
+    /// a poisoned lock still gives it, inside the error
     #[inline(always)]
-    pub const fn new(t: T) -> Self {
-        Self {
-            version_lock_outdate: AtomicU64::new(0),
-            poisoned: AtomicBool::new(false),
-            data: UnsafeCell::new(t),
+    pub fn into_inner(self) -> Result<T, OptimisticLockCouplingPoisonError<T>> {
+        let poisoned = self.is_poisoned();
+        let data = self.data.into_inner();
+        if poisoned {
+            Err(OptimisticLockCouplingPoisonError { guard: data })
+        } else {
+            Ok(data)
         }
     }
     /// read transaction
     /// logic should be an inlined closure
+    /// waits with the default `ExponentialBackoff` between retries
     #[inline(always)]
-    pub fn read_txn<F, R>(&self, mut logic: F) -> OptimisticLockCouplingResult<R>
+    pub fn read_txn<F, R>(&self, logic: F) -> OptimisticLockCouplingResult<R>
     where
-        F: FnMut(&OptimisticLockCouplingReadGuard<T>) -> OptimisticLockCouplingResult<R>,
+        F: FnMut(&OptimisticLockCouplingReadGuard<T, W>) -> OptimisticLockCouplingResult<R>,
     {
+        self.read_txn_with(ExponentialBackoff::default(), logic)
+    }
+    /// read transaction with your own `Backoff` between retries
+    #[inline(always)]
+    pub fn read_txn_with<B, F, R>(
+        &self,
+        mut backoff: B,
+        mut logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        B: Backoff,
+        F: FnMut(&OptimisticLockCouplingReadGuard<T, W>) -> OptimisticLockCouplingResult<R>,
+    {
+        backoff.reset();
         'txn: loop {
-            match self.read() {
+            // SAFETY: logic only borrows the guard, it is validated before `r` is returned
+            match unsafe { self.read() } {
                 Ok(guard) => match logic(&guard) {
                     Ok(r) => match guard.try_sync() {
                         Ok(_) => {
@@ -174,16 +416,19 @@ impl<T> OptimisticLockCoupling<T> {
                                 return Err(e);
                             }
                             _ => {
+                                backoff.backoff();
                                 continue 'txn;
                             }
                         },
                     },
                     Err(e) => match e {
                         OptimisticLockCouplingErrorType::Poisoned
-                        | OptimisticLockCouplingErrorType::Outdated => {
+                        | OptimisticLockCouplingErrorType::Outdated
+                        | OptimisticLockCouplingErrorType::TimedOut => {
                             return Err(e);
                         }
                         _ => {
+                            backoff.backoff();
                             continue 'txn;
                         }
                     },
@@ -194,6 +439,7 @@ impl<T> OptimisticLockCoupling<T> {
                         return Err(e);
                     }
                     _ => {
+                        backoff.backoff();
                         continue 'txn;
                     }
                 },
@@ -201,78 +447,712 @@ impl<T> OptimisticLockCoupling<T> {
         }
     }
 }
-impl<T: Sized> From<T> for OptimisticLockCoupling<T> {
+impl<T: NoUninit, W: LockWord> OptimisticLockCoupling<T, W> {
+    /// copy the data out without ever handing out a reference a writer could race with
+    /// retries until the copy is known to be consistent, seqlock style
+    /// only `Poisoned` and `Outdated` are returned
+    ///
+    /// the copy is made one relaxed atomic byte load at a time, so it can't be torn below a byte
+    /// or moved out from between the two version checks. The writer still stores plainly,
+    /// that race is the one every seqlock has and a torn copy is never returned.
+    /// Every byte is loaded, that is why `T` can't have padding, see `NoUninit`
+    /// waits with the default `ExponentialBackoff` between retries
+    #[inline(always)]
+    pub fn load(&self) -> OptimisticLockCouplingResult<T> {
+        self.load_with(ExponentialBackoff::default())
+    }
+    /// `load` with your own `Backoff` between retries
+    pub fn load_with<B: Backoff>(&self, mut backoff: B) -> OptimisticLockCouplingResult<T> {
+        use OptimisticLockCouplingErrorType::*;
+        backoff.reset();
+        loop {
+            match self.try_lock() {
+                Ok(version) => {
+                    // it stays `MaybeUninit` because it may be torn until validated
+                    let value = unsafe { self.copy_bytes() };
+                    match self.validate(version) {
+                        Ok(_) => return Ok(unsafe { value.assume_init() }),
+                        Err(e @ (Poisoned | Outdated)) => return Err(e),
+                        Err(_) => {}
+                    }
+                }
+                Err(e @ (Poisoned | Outdated)) => return Err(e),
+                Err(_) => {}
+            }
+            backoff.backoff();
+        }
+    }
+    /// the data, copied with relaxed atomic byte loads, ordered by the `Acquire` fence in `validate`
+    /// always `core`'s `AtomicU8`, loom can't lay its own over memory it didn't create
+    ///
+    /// # Safety
+    /// the copy may be torn, don't assume it is a `T` before the version was validated
+    #[inline(always)]
+    unsafe fn copy_bytes(&self) -> MaybeUninit<T> {
+        use core::sync::atomic::AtomicU8;
+        let mut value = MaybeUninit::<T>::uninit();
+        let src = self.data.get() as *const AtomicU8;
+        let dst = value.as_mut_ptr() as *mut u8;
+        for i in 0..core::mem::size_of::<T>() {
+            *dst.add(i) = (*src.add(i)).load(Relaxed);
+        }
+        value
+    }
+}
+#[cfg(target_has_atomic = "64")]
+impl<T: Sized> From<T> for OptimisticLockCoupling<T, u64> {
     #[inline(always)]
     fn from(t: T) -> Self {
         Self::new(t)
     }
 }
-impl<T: ?Sized + Default> Default for OptimisticLockCoupling<T> {
+#[cfg(target_has_atomic = "64")]
+impl<T: Default> Default for OptimisticLockCoupling<T, u64> {
     #[inline(always)]
     fn default() -> Self {
         Self::new(Default::default())
     }
 }
 
-impl<T: ?Sized> OptimisticLockCoupling<T> {
+impl<T: ?Sized, W: LockWord> OptimisticLockCoupling<T, W> {
     /// make self outdate
     /// usually used when the container grows and this pointer point to this structure is replaced
+    ///
+    /// it doesn't need the lock, so a reader may see the node after the writer left but before this,
+    /// `OptimisticLockCouplingWriteGuard::unlock_obsolete` does both in one step
+    #[deprecated(note = "release the write guard with `unlock_obsolete` instead")]
     #[inline(always)]
     pub fn make_outdate(&self) {
-        self.version_lock_outdate.fetch_or(0b1, Release);
+        W::fetch_or(&self.version_lock_outdate, W::OUTDATE, Release);
+    }
+    /// what happens when the version runs out
+    #[inline(always)]
+    pub fn overflow_policy(&self) -> VersionOverflowPolicy {
+        if W::load(&self.version_lock_outdate, Relaxed) & W::OUTDATE_ON_OVERFLOW != W::ZERO {
+            VersionOverflowPolicy::Outdate
+        } else {
+            VersionOverflowPolicy::Panic
+        }
+    }
+    /// change what happens when the version runs out
+    #[inline(always)]
+    pub fn set_overflow_policy(&self, policy: VersionOverflowPolicy) {
+        match policy {
+            VersionOverflowPolicy::Panic => {
+                W::fetch_and(&self.version_lock_outdate, !W::OUTDATE_ON_OVERFLOW, Relaxed)
+            }
+            VersionOverflowPolicy::Outdate => {
+                W::fetch_or(&self.version_lock_outdate, W::OUTDATE_ON_OVERFLOW, Relaxed)
+            }
+        };
+    }
+    /// release the lock held by a write guard, returns the new word
+    /// the bits in `mark` (poison, outdate) are set in the same step
+    #[inline(always)]
+    fn unlock(&self, mark: W) -> W {
+        unlock_word(&self.version_lock_outdate, mark)
     }
     /// is writter thread dead?
     /// if fail then fail ~
     /// no need extra sync
     #[inline(always)]
     pub fn is_poisoned(&self) -> bool {
-        self.poisoned.load(std::sync::atomic::Ordering::Acquire)
+        is_poisoned(W::load(&self.version_lock_outdate, Acquire))
     }
-    /// try to aquire the lock but only internal use
+    /// forget that a writer once panicked
+    /// do it after the data is repaired, e.g. with the guard from `try_write`
     #[inline(always)]
-    fn try_lock(&self) -> OptimisticLockCouplingResult<u64> {
-        use OptimisticLockCouplingErrorType::*;
-        if self.is_poisoned() {
-            return Err(Poisoned);
-        }
-        let version = self.version_lock_outdate.load(Acquire);
-        if is_outdate(version) {
-            return Err(Outdated);
+    pub fn clear_poison(&self) {
+        W::fetch_and(&self.version_lock_outdate, !W::POISON, Release);
+    }
+    /// the data without locking, the borrow checker already made sure no one else can hold the lock
+    /// a poisoned lock still gives it, inside the error
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> Result<&mut T, OptimisticLockCouplingPoisonError<&mut T>> {
+        let poisoned = self.is_poisoned();
+        let data = self.data.get_mut();
+        if poisoned {
+            Err(OptimisticLockCouplingPoisonError { guard: data })
+        } else {
+            Ok(data)
         }
-        if is_locked(version) {
-            return Err(Blocked);
+    }
+    /// the raw pointer to the data, every access through it is up to you
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *mut T {
+        self.data.get()
+    }
+    /// try to aquire the lock but only internal use
+    #[inline(always)]
+    fn try_lock(&self) -> OptimisticLockCouplingResult<W> {
+        try_lock_word(&self.version_lock_outdate)
+    }
+    /// take the lock if the word still holds `version`
+    #[inline(always)]
+    fn lock_version(&self, version: W) -> bool {
+        let locked = W::compare_exchange(
+            &self.version_lock_outdate,
+            version,
+            version + W::LOCK,
+            Acquire,
+            Relaxed,
+        )
+        .is_ok();
+        if locked {
+            // the writes to the data must not become visible before the lock bit,
+            // pairs with the fence in `validate`
+            fence(Release);
         }
-        Ok(version)
+        locked
+    }
+    /// is the lock still at `version`? call it after the reads it should cover
+    #[inline(always)]
+    fn validate(&self, version: W) -> OptimisticLockCouplingResult<()> {
+        validate_word(&self.version_lock_outdate, version)
+    }
+    /// the version checks of `try_lock` only
+    #[inline(always)]
+    fn try_lock_ignore_poison(&self) -> OptimisticLockCouplingResult<W> {
+        check_version(W::load(&self.version_lock_outdate, Acquire))
     }
+    /// the raw read guard
     /// I suggest you redo the hole function when error occurs
-    /// Or just use `read_txn`
+    /// Or just use `read_with` / `read_txn`
+    ///
+    /// # Safety
+    /// the guard derefs to data a writer may change at any time,
+    /// nothing read through it may be used after a failed `try_sync`, or without calling it
     #[inline(always)]
-    pub fn read(&self) -> OptimisticLockCouplingResult<OptimisticLockCouplingReadGuard<'_, T>> {
+    pub unsafe fn read(
+        &self,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingReadGuard<'_, T, W>> {
         OptimisticLockCouplingReadGuard::new(self)
     }
+    /// read once, the safe way
+    /// `logic` only sees the data inside a validated attempt and can't return references into it
+    /// fails like `read` + `try_sync` would, nothing is retried
+    #[inline(always)]
+    pub fn read_with<F, R>(&self, logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        // SAFETY: the result is only handed out after `try_sync`
+        let guard = unsafe { self.read() }?;
+        let r = logic(&guard);
+        guard.try_sync().map(|_| r)
+    }
     /// get your RAII write guard
     #[inline(always)]
-    pub fn write(&self) -> OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'_, T>> {
+    pub fn write(
+        &self,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'_, T, W>> {
         use OptimisticLockCouplingErrorType::*;
+        // poison is checked on the very word the lock is taken at,
+        // a writer that panics in between changes the word and the CAS fails
         let version = self.try_lock()?;
-        match self
-            .version_lock_outdate
-            .compare_exchange(version, version + 0b10, Acquire, Acquire)
-        {
-            Ok(_) => Ok(OptimisticLockCouplingWriteGuard::new(self)),
-            Err(_) => Err(VersionUpdated),
+        if self.lock_version(version) {
+            Ok(OptimisticLockCouplingWriteGuard::new(self))
+        } else {
+            Err(VersionUpdated)
+        }
+    }
+    /// like `write` but a poisoned lock still hands out its guard, inside the error
+    #[inline(always)]
+    pub fn try_write(
+        &self,
+    ) -> Result<
+        OptimisticLockCouplingWriteGuard<'_, T, W>,
+        OptimisticLockCouplingTryLockError<OptimisticLockCouplingWriteGuard<'_, T, W>>,
+    > {
+        match self.write_ignore_poison() {
+            Ok(guard) if self.is_poisoned() => Err(OptimisticLockCouplingTryLockError::Poisoned(
+                OptimisticLockCouplingPoisonError { guard },
+            )),
+            Ok(guard) => Ok(guard),
+            Err(e) => Err(OptimisticLockCouplingTryLockError::Failed(e)),
+        }
+    }
+    /// get your RAII write guard even if a writer panicked before
+    /// the poison stays until `clear_poison`
+    #[inline(always)]
+    pub fn write_ignore_poison(
+        &self,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'_, T, W>> {
+        use OptimisticLockCouplingErrorType::*;
+        let version = self.try_lock_ignore_poison()?;
+        if self.lock_version(version) {
+            Ok(OptimisticLockCouplingWriteGuard::new(self))
+        } else {
+            Err(VersionUpdated)
+        }
+    }
+    /// write transaction
+    /// retries until the lock is acquired, then hands the guard to logic
+    /// only `Poisoned` and `Outdated` are returned
+    /// waits with the default `ExponentialBackoff` between retries
+    #[inline(always)]
+    pub fn write_txn<F, R>(&self, logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnOnce(&mut OptimisticLockCouplingWriteGuard<T, W>) -> R,
+    {
+        self.write_txn_with(ExponentialBackoff::default(), logic)
+    }
+    /// write transaction with your own `Backoff` between retries
+    #[inline(always)]
+    pub fn write_txn_with<B, F, R>(
+        &self,
+        mut backoff: B,
+        logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        B: Backoff,
+        F: FnOnce(&mut OptimisticLockCouplingWriteGuard<T, W>) -> R,
+    {
+        backoff.reset();
+        'txn: loop {
+            match self.write() {
+                Ok(mut guard) => {
+                    return Ok(logic(&mut guard));
+                }
+                Err(e) => match e {
+                    OptimisticLockCouplingErrorType::Poisoned
+                    | OptimisticLockCouplingErrorType::Outdated => {
+                        return Err(e);
+                    }
+                    _ => {
+                        backoff.backoff();
+                        continue 'txn;
+                    }
+                },
+            }
         }
     }
 }
 
+/// descents that keep their path, guards that own their lock
+#[cfg(feature = "alloc")]
+impl<T: ?Sized, W: LockWord> OptimisticLockCoupling<T, W> {
+    /// like `read` but the guard holds a clone of the `Arc` instead of a borrow
+    ///
+    /// # Safety
+    /// same as `read`
+    #[inline(always)]
+    pub unsafe fn read_arc(
+        self: &alloc::sync::Arc<Self>,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingArcReadGuard<T, W>> {
+        OptimisticLockCouplingArcReadGuard::new(self.clone())
+    }
+    /// like `write` but the guard holds a clone of the `Arc` instead of a borrow
+    #[inline(always)]
+    pub fn write_arc(
+        self: &alloc::sync::Arc<Self>,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingArcWriteGuard<T, W>> {
+        let guard = self.write()?;
+        // the owned guard does the release now
+        core::mem::forget(guard);
+        Ok(OptimisticLockCouplingArcWriteGuard::new(self.clone()))
+    }
+    /// read transaction over a tree whose nodes are all `OptimisticLockCoupling<T, W>`, `self` is the root
+    /// logic descends with `OlcPath::descend` from `OlcPath::last`,
+    /// when it fails only the nodes that changed are given up, the next attempt starts
+    /// from the deepest node that is still valid, with the path as it was down to there
+    ///
+    /// the whole path is validated before `r` is returned
+    /// `Poisoned` and `TimedOut` end the transaction, so does an outdated root,
+    /// an outdated node below it only means it was replaced and its parent is read again
+    /// waits with the default `ExponentialBackoff` between retries
+    #[inline(always)]
+    pub fn read_path_txn<F, R>(&self, logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnMut(&mut OlcPath<'_, T, W>) -> OptimisticLockCouplingResult<R>,
+    {
+        self.read_path_txn_with(ExponentialBackoff::default(), logic)
+    }
+    /// `read_path_txn` with your own `Backoff` between retries
+    pub fn read_path_txn_with<B, F, R>(
+        &self,
+        mut backoff: B,
+        mut logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        B: Backoff,
+        F: FnMut(&mut OlcPath<'_, T, W>) -> OptimisticLockCouplingResult<R>,
+    {
+        use OptimisticLockCouplingErrorType::*;
+        backoff.reset();
+        let mut path = OlcPath::new();
+        'txn: loop {
+            path.restart();
+            if path.is_empty() {
+                // SAFETY: logic only borrows the path, it is validated before `r` is returned
+                match unsafe { self.read() } {
+                    Ok(root) => path.push(root),
+                    Err(e @ (Poisoned | Outdated)) => return Err(e),
+                    Err(_) => {
+                        backoff.backoff();
+                        continue 'txn;
+                    }
+                }
+            }
+            match logic(&mut path).and_then(|r| path.check().map(|_| r)) {
+                Ok(r) => {
+                    return Ok(r);
+                }
+                Err(e @ (Poisoned | TimedOut)) => {
+                    return Err(e);
+                }
+                Err(_) => {
+                    backoff.backoff();
+                    continue 'txn;
+                }
+            }
+        }
+    }
+}
+
+/// a slice lock in one allocation, the elements are moved, not cloned
+#[cfg(feature = "alloc")]
+impl<T, W: LockWord> From<alloc::boxed::Box<[T]>>
+    for alloc::boxed::Box<OptimisticLockCoupling<[T], W>>
+{
+    fn from(data: alloc::boxed::Box<[T]>) -> Self {
+        use alloc::alloc::{alloc, handle_alloc_error, Layout};
+        use alloc::boxed::Box;
+        use core::{mem::ManuallyDrop, ptr};
+        let len = data.len();
+        // the `repr(C)` layout: the word, then the elements
+        let (layout, offset) = Layout::new::<W::Atomic>()
+            .extend(Layout::array::<T>(len).expect("slice too large"))
+            .expect("slice too large");
+        let layout = layout.pad_to_align();
+        unsafe {
+            let raw = alloc(layout);
+            if raw.is_null() {
+                handle_alloc_error(layout);
+            }
+            raw.cast::<W::Atomic>().write(W::new_atomic(W::ZERO));
+            let src = Box::into_raw(data);
+            ptr::copy_nonoverlapping(src as *const T, raw.add(offset).cast::<T>(), len);
+            // free the old allocation, the elements live on in the new one
+            drop(Box::from_raw(src as *mut [ManuallyDrop<T>]));
+            let fat = ptr::slice_from_raw_parts_mut(raw.cast::<T>(), len);
+            Box::from_raw(fat as *mut OptimisticLockCoupling<[T], W>)
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T, W: LockWord> From<alloc::vec::Vec<T>>
+    for alloc::boxed::Box<OptimisticLockCoupling<[T], W>>
+{
+    #[inline(always)]
+    fn from(data: alloc::vec::Vec<T>) -> Self {
+        data.into_boxed_slice().into()
+    }
+}
+
+/// everything that needs to put a thread or a task to sleep
+#[cfg(feature = "std")]
+impl<T: ?Sized, W: LockWord> OptimisticLockCoupling<T, W> {
+    /// locked by a writer that is still alive
+    #[inline(always)]
+    fn is_blocked(&self) -> bool {
+        let word = W::load(&self.version_lock_outdate, Acquire);
+        !is_poisoned(word) && is_locked(word)
+    }
+    /// the key of this lock in the parking table
+    #[inline(always)]
+    fn addr(&self) -> usize {
+        &self.version_lock_outdate as *const W::Atomic as usize
+    }
+    /// like `read` but sleeps while a writer holds the lock instead of failing with `Blocked`
+    /// woken up when the writer drops its guard
+    ///
+    /// # Safety
+    /// same as `read`
+    #[inline(always)]
+    pub unsafe fn read_blocking(
+        &self,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingReadGuard<'_, T, W>> {
+        use OptimisticLockCouplingErrorType::*;
+        loop {
+            match self.read() {
+                Err(Blocked) => parking::park(self.addr(), || self.is_blocked()),
+                res => return res,
+            }
+        }
+    }
+    /// like `write` but sleeps while another writer holds the lock instead of failing with `Blocked`
+    /// woken up when the writer drops its guard
+    #[inline(always)]
+    pub fn write_blocking(
+        &self,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'_, T, W>> {
+        use OptimisticLockCouplingErrorType::*;
+        let mut backoff = ExponentialBackoff::default();
+        loop {
+            match self.write() {
+                Err(Blocked) => parking::park(self.addr(), || self.is_blocked()),
+                // lost the race against another writer, try again
+                Err(VersionUpdated) => backoff.backoff(),
+                res => return res,
+            }
+        }
+    }
+    /// like `write_blocking` but gives up with `TimedOut` after `timeout`
+    /// a timeout too long for an `Instant` never runs out
+    #[inline(always)]
+    pub fn try_write_for(
+        &self,
+        timeout: Duration,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'_, T, W>> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.try_write_until(deadline),
+            None => self.write_blocking(),
+        }
+    }
+    /// like `write_blocking` but gives up with `TimedOut` once `deadline` passes
+    pub fn try_write_until(
+        &self,
+        deadline: Instant,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'_, T, W>> {
+        use OptimisticLockCouplingErrorType::*;
+        let mut backoff = ExponentialBackoff::default();
+        loop {
+            match self.write() {
+                Err(Blocked) => {
+                    if !parking::park_until(self.addr(), || self.is_blocked(), deadline) {
+                        return Err(TimedOut);
+                    }
+                }
+                Err(VersionUpdated) => {
+                    if Instant::now() >= deadline {
+                        return Err(TimedOut);
+                    }
+                    backoff.backoff();
+                }
+                res => return res,
+            }
+        }
+    }
+    /// the async version of `write_blocking`
+    /// the task is woken when the writer drops its guard, works with any executor
+    #[inline(always)]
+    pub fn write_async(&self) -> OptimisticLockCouplingWriteFuture<'_, T, W> {
+        OptimisticLockCouplingWriteFuture::new(self)
+    }
+    /// the async version of `read_txn`
+    /// waits for the writer without holding the thread and yields to the executor before every restart
+    /// dropping the future at any point leaves the lock untouched
+    /// only a `SpinBackoff` hint before every restart, nothing that would hold the executor thread
+    #[inline(always)]
+    pub async fn read_txn_async<F, R>(&self, logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnMut(&OptimisticLockCouplingReadGuard<T, W>) -> OptimisticLockCouplingResult<R>,
+    {
+        self.read_txn_async_with(SpinBackoff, logic).await
+    }
+    /// `read_txn_async` with your own `Backoff` before every restart
+    /// it runs on the executor thread, one that sleeps stalls every task on it
+    pub async fn read_txn_async_with<B, F, R>(
+        &self,
+        mut backoff: B,
+        mut logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        B: Backoff,
+        F: FnMut(&OptimisticLockCouplingReadGuard<T, W>) -> OptimisticLockCouplingResult<R>,
+    {
+        use OptimisticLockCouplingErrorType::*;
+        backoff.reset();
+        'txn: loop {
+            // the guard is not `Send`, it must be gone before any `.await`
+            // SAFETY: logic only borrows the guard, it is validated before `r` is returned
+            let res = match unsafe { self.read() } {
+                Ok(guard) => logic(&guard).and_then(|r| guard.try_sync().map(|_| r)),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(r) => {
+                    return Ok(r);
+                }
+                Err(e @ (Poisoned | Outdated | TimedOut)) => {
+                    return Err(e);
+                }
+                Err(Blocked) if self.is_blocked() => {
+                    future::Unblocked::new(self).await;
+                    continue 'txn;
+                }
+                Err(_) => {
+                    backoff.backoff();
+                    future::YieldNow::default().await;
+                    continue 'txn;
+                }
+            }
+        }
+    }
+    /// read transaction that gives up with `TimedOut` once `deadline` passes
+    /// sleeps while a writer holds the lock, the same way `read_blocking` does
+    /// waits with the default `ExponentialBackoff` between retries
+    #[inline(always)]
+    pub fn read_txn_until<F, R>(
+        &self,
+        deadline: Instant,
+        logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnMut(&OptimisticLockCouplingReadGuard<T, W>) -> OptimisticLockCouplingResult<R>,
+    {
+        self.read_txn_until_with(deadline, ExponentialBackoff::default(), logic)
+    }
+    /// `read_txn_until` with your own `Backoff` between retries
+    pub fn read_txn_until_with<B, F, R>(
+        &self,
+        deadline: Instant,
+        mut backoff: B,
+        mut logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        B: Backoff,
+        F: FnMut(&OptimisticLockCouplingReadGuard<T, W>) -> OptimisticLockCouplingResult<R>,
+    {
+        use OptimisticLockCouplingErrorType::*;
+        backoff.reset();
+        'txn: loop {
+            // SAFETY: logic only borrows the guard, it is validated before `r` is returned
+            let res = match unsafe { self.read() } {
+                Ok(guard) => logic(&guard).and_then(|r| guard.try_sync().map(|_| r)),
+                Err(Blocked) => {
+                    if !parking::park_until(self.addr(), || self.is_blocked(), deadline) {
+                        return Err(TimedOut);
+                    }
+                    continue 'txn;
+                }
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(r) => {
+                    return Ok(r);
+                }
+                Err(e @ (Poisoned | Outdated | TimedOut)) => {
+                    return Err(e);
+                }
+                Err(_) => {
+                    if Instant::now() >= deadline {
+                        return Err(TimedOut);
+                    }
+                    backoff.backoff();
+                    continue 'txn;
+                }
+            }
+        }
+    }
+}
+
+/// is the current thread unwinding?
+/// without `std` there is no way to tell, a writer is never considered panicking,
+/// so its guard releases the lock as usual and the lock is never poisoned
+#[inline(always)]
+fn panicking() -> bool {
+    #[cfg(feature = "std")]
+    {
+        std::thread::panicking()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        false
+    }
+}
+
+#[inline]
+fn version_of<W: LockWord>(word: W) -> W {
+    (word & !(W::POISON | W::OUTDATE_ON_OVERFLOW)) >> 2
+}
+
+#[inline]
+fn is_locked<W: LockWord>(version: W) -> bool {
+    version & W::LOCK != W::ZERO
+}
+
 #[inline]
-const fn is_locked(version: u64) -> bool {
-    version & 0b10 != 0
+fn is_outdate<W: LockWord>(version: W) -> bool {
+    version & W::OUTDATE != W::ZERO
 }
 
 #[inline]
-const fn is_outdate(version: u64) -> bool {
-    version & 0b1 != 0
+fn is_poisoned<W: LockWord>(version: W) -> bool {
+    version & W::POISON != W::ZERO
+}
+
+/// `unlock` on the word alone, for the mapped write guard
+#[inline(always)]
+fn unlock_word<W: LockWord>(atomic: &W::Atomic, mark: W) -> W {
+    // the version is ours while we hold the lock,
+    // `make_outdate` and `set_overflow_policy` only touch the other bits
+    let word = W::load(atomic, Relaxed);
+    let release = |w: W| {
+        if version_of(w) != W::MAX_VERSION {
+            (w + W::LOCK) | mark
+        } else {
+            // out of versions, never wrap: leave the lock unlocked but outdated
+            (w | W::OUTDATE | mark) & !W::LOCK
+        }
+    };
+    // `SeqCst` so `unpark_all` can look for waiters without a fence of its own,
+    // on x86 it is the same locked instruction as `Release`
+    let released = if version_of(word) != W::MAX_VERSION && mark == W::ZERO {
+        W::fetch_add(atomic, W::LOCK, SeqCst) + W::LOCK
+    } else {
+        let word = W::fetch_update(atomic, SeqCst, Relaxed, |w| Some(release(w))).unwrap_or(word);
+        release(word)
+    };
+    #[cfg(feature = "std")]
+    parking::unpark_all(atomic as *const W::Atomic as usize);
+    if is_outdate(released)
+        && version_of(released) == W::MAX_VERSION
+        && released & W::OUTDATE_ON_OVERFLOW == W::ZERO
+        && mark & W::OUTDATE == W::ZERO
+        && !panicking()
+    {
+        panic!("the version of an OptimisticLockCoupling overflowed, it is outdated now");
+    }
+    released
+}
+
+/// `try_lock` on the word alone, mapped guards don't know the type of the lock
+#[inline(always)]
+fn try_lock_word<W: LockWord>(word: &W::Atomic) -> OptimisticLockCouplingResult<W> {
+    use OptimisticLockCouplingErrorType::*;
+    let version = W::load(word, Acquire);
+    if is_poisoned(version) {
+        return Err(Poisoned);
+    }
+    check_version(version)
+}
+
+/// `validate` on the word alone
+#[inline(always)]
+fn validate_word<W: LockWord>(word: &W::Atomic, version: W) -> OptimisticLockCouplingResult<()> {
+    // the reads of the data must not move below the re-check,
+    // a plain `Acquire` load only orders what comes after it
+    fence(Acquire);
+    if version == try_lock_word(word)? {
+        Ok(())
+    } else {
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    }
+}
+
+/// the version checks shared by every way of taking the lock
+#[inline(always)]
+fn check_version<W: LockWord>(version: W) -> OptimisticLockCouplingResult<W> {
+    use OptimisticLockCouplingErrorType::*;
+    if is_outdate(version) {
+        return Err(Outdated);
+    }
+    if is_locked(version) {
+        return Err(Blocked);
+    }
+    Ok(version)
 }
 
 // ============= reader guard =============== //
@@ -281,36 +1161,97 @@ const fn is_outdate(version: u64) -> bool {
 /// after getting the guard you can do what ever you want with Deref
 /// but after usage you **MUST** call `try_sync`
 /// if fails you must redo the hole function or other sync method to ensure the data you read is correct.
-pub struct OptimisticLockCouplingReadGuard<'a, T: ?Sized + 'a> {
-    lock: &'a OptimisticLockCoupling<T>,
-    version: u64,
+///
+/// the guard belongs to the thread that read the version, it is not `Send`
+/// ```compile_fail
+/// use optimistic_lock_coupling::OptimisticLockCoupling;
+/// fn assert_send<T: Send>(_: T) {}
+/// let lock = OptimisticLockCoupling::new(1);
+/// assert_send(unsafe { lock.read() }.unwrap());
+/// ```
+pub struct OptimisticLockCouplingReadGuard<'a, T: ?Sized + 'a, W: LockWord = DefaultLockWord> {
+    lock: &'a OptimisticLockCoupling<T, W>,
+    version: W,
+    /// raw pointers are not `Send`, so neither is the guard
+    _not_send: PhantomData<*const ()>,
 }
-impl<'a, T: ?Sized> OptimisticLockCouplingReadGuard<'a, T> {
+impl<'a, T: ?Sized, W: LockWord> OptimisticLockCouplingReadGuard<'a, T, W> {
     #[inline(always)]
-    pub fn new(lock: &'a OptimisticLockCoupling<T>) -> OptimisticLockCouplingResult<Self> {
+    pub(crate) fn new(
+        lock: &'a OptimisticLockCoupling<T, W>,
+    ) -> OptimisticLockCouplingResult<Self> {
+        let version = lock.try_lock()?;
+        Ok(Self {
+            lock,
+            version,
+            _not_send: PhantomData,
+        })
+    }
+}
+/// the marker takes `Sync` away as well, give it back
+unsafe impl<T: ?Sized + Send + Sync, W: LockWord> Sync
+    for OptimisticLockCouplingReadGuard<'_, T, W>
+{
+}
+#[cfg(feature = "nightly")]
+impl<T: ?Sized, W: LockWord> !Send for OptimisticLockCouplingReadGuard<'_, T, W> {}
+impl<'a, T: ?Sized, W: LockWord> OptimisticLockCouplingReadGuard<'a, T, W> {
+    /// Consume self and try to turn the read into a write
+    /// without releasing what you have seen
+    /// fails with `VersionUpdated` if any writer touched the data after the read
+    #[inline(always)]
+    pub fn upgrade(
+        self,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingWriteGuard<'a, T, W>> {
         use crate::OptimisticLockCouplingErrorType::*;
-        if lock.is_poisoned() {
+        if self.lock.is_poisoned() {
             return Err(Poisoned);
         }
-        let version = lock.try_lock()?;
-        Ok(Self { lock, version })
+        if self.lock.lock_version(self.version) {
+            Ok(OptimisticLockCouplingWriteGuard::new(self.lock))
+        } else {
+            Err(VersionUpdated)
+        }
     }
-}
-impl<T: ?Sized> !Send for OptimisticLockCouplingReadGuard<'_, T> {}
-impl<T: ?Sized> OptimisticLockCouplingReadGuard<'_, T> {
     /// Consume self return retry or not
     /// suggest to use `read_txn`
     #[inline(always)]
     pub fn try_sync(self) -> OptimisticLockCouplingResult<()> {
-        if self.version == self.lock.try_lock()? {
-            drop(self);
-            Ok(())
-        } else {
-            Err(crate::OptimisticLockCouplingErrorType::VersionUpdated)
-        }
+        self.lock.validate(self.version)
+    }
+    /// Consume self and move on to a child lock, hand over hand
+    /// the paper's "read child, then check parent" in one call:
+    /// `child` picks the child lock from the data, the parent is validated before it is followed
+    /// and again after the child's version is read, then released
+    ///
+    /// everything read through this guard before is validated too
+    #[inline(always)]
+    pub fn couple<U, V, F>(
+        self,
+        child: F,
+    ) -> OptimisticLockCouplingResult<OptimisticLockCouplingReadGuard<'a, U, V>>
+    where
+        U: ?Sized,
+        V: LockWord,
+        F: FnOnce(&'a T) -> &'a OptimisticLockCoupling<U, V>,
+    {
+        // SAFETY: same as `Deref`, the pointer may be torn until the parent says otherwise
+        let child = child(unsafe { &*self.lock.data.get() });
+        self.check()?;
+        let child = OptimisticLockCouplingReadGuard::new(child)?;
+        // the child was still linked when its version was read
+        self.try_sync()?;
+        Ok(child)
+    }
+    /// validate without giving the guard up, the paper's `checkOrRestart`
+    /// everything read before the call is consistent if it succeeds, keep reading after it
+    /// do it before following a pointer you read from the data
+    #[inline(always)]
+    pub fn check(&self) -> OptimisticLockCouplingResult<()> {
+        self.lock.validate(self.version)
     }
 }
-impl<T: ?Sized> Deref for OptimisticLockCouplingReadGuard<'_, T> {
+impl<T: ?Sized, W: LockWord> Deref for OptimisticLockCouplingReadGuard<'_, T, W> {
     type Target = T;
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
@@ -318,83 +1259,276 @@ impl<T: ?Sized> Deref for OptimisticLockCouplingReadGuard<'_, T> {
     }
 }
 
-impl<T: Debug> Debug for OptimisticLockCouplingReadGuard<'_, T> {
+impl<T: Debug, W: LockWord> Debug for OptimisticLockCouplingReadGuard<'_, T, W> {
     #[inline(always)]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("OptimisticLockCouplingReadGuard")
-            .field("version", &(self.version >> 2))
+            .field("version", &version_of(self.version))
             .field("data", self.deref())
             .finish()
     }
 }
-impl<T: Debug + Display> Display for OptimisticLockCouplingReadGuard<'_, T> {
+impl<T: Debug + Display, W: LockWord> Display for OptimisticLockCouplingReadGuard<'_, T, W> {
     #[inline(always)]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_fmt(format_args!(
             "OptimisticLockCouplingReadGuard (ver: {}) {}",
-            self.version >> 2,
+            version_of(self.version),
             self.deref()
         ))
     }
 }
 
+// ============= mapped reader guard =============== //
+
+/// A read guard that only sees a part of the data, made by `OptimisticLockCouplingReadGuard::map`
+/// still validates against the version of the whole lock, same rules as the read guard
+pub struct OptimisticLockCouplingMappedReadGuard<'a, U: ?Sized + 'a, W: LockWord = DefaultLockWord>
+{
+    word: &'a W::Atomic,
+    version: W,
+    data: *const U,
+    /// borrows the lock like the read guard does
+    _lock: PhantomData<&'a U>,
+}
+/// same as the read guard, the raw pointer takes `Send` away already
+unsafe impl<U: ?Sized + Sync, W: LockWord> Sync
+    for OptimisticLockCouplingMappedReadGuard<'_, U, W>
+{
+}
+#[cfg(feature = "nightly")]
+impl<U: ?Sized, W: LockWord> !Send for OptimisticLockCouplingMappedReadGuard<'_, U, W> {}
+impl<'a, T: ?Sized, W: LockWord> OptimisticLockCouplingReadGuard<'a, T, W> {
+    /// Consume the guard and keep only the part `f` picks
+    /// an associated function so it doesn't hide a `map` of `T`
+    #[inline(always)]
+    pub fn map<U, F>(guard: Self, f: F) -> OptimisticLockCouplingMappedReadGuard<'a, U, W>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> &U,
+    {
+        OptimisticLockCouplingMappedReadGuard {
+            word: &guard.lock.version_lock_outdate,
+            version: guard.version,
+            data: f(&guard),
+            _lock: PhantomData,
+        }
+    }
+}
+impl<'a, U: ?Sized, W: LockWord> OptimisticLockCouplingMappedReadGuard<'a, U, W> {
+    /// narrow it down further
+    #[inline(always)]
+    pub fn map<V, F>(guard: Self, f: F) -> OptimisticLockCouplingMappedReadGuard<'a, V, W>
+    where
+        V: ?Sized,
+        F: FnOnce(&U) -> &V,
+    {
+        OptimisticLockCouplingMappedReadGuard {
+            word: guard.word,
+            version: guard.version,
+            data: f(&guard),
+            _lock: PhantomData,
+        }
+    }
+    /// Consume self return retry or not, against the whole lock
+    #[inline(always)]
+    pub fn try_sync(self) -> OptimisticLockCouplingResult<()> {
+        validate_word(self.word, self.version)
+    }
+    /// validate without giving the guard up
+    #[inline(always)]
+    pub fn check(&self) -> OptimisticLockCouplingResult<()> {
+        validate_word(self.word, self.version)
+    }
+}
+impl<U: ?Sized, W: LockWord> Deref for OptimisticLockCouplingMappedReadGuard<'_, U, W> {
+    type Target = U;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.data }
+    }
+}
+impl<U: Debug + ?Sized, W: LockWord> Debug for OptimisticLockCouplingMappedReadGuard<'_, U, W> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OptimisticLockCouplingMappedReadGuard")
+            .field("version", &version_of(self.version))
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
 // ============= writer guard =============== //
 
 /// Only one instance because the data is locked
 /// implemented `Deref` and `DerefMut`
 /// release the lock on drop
-pub struct OptimisticLockCouplingWriteGuard<'a, T: ?Sized + 'a> {
-    lock: &'a OptimisticLockCoupling<T>,
+pub struct OptimisticLockCouplingWriteGuard<'a, T: ?Sized + 'a, W: LockWord = DefaultLockWord> {
+    lock: &'a OptimisticLockCoupling<T, W>,
 }
-unsafe impl<T: ?Sized + Sync> Sync for OptimisticLockCouplingWriteGuard<'_, T> {}
-impl<T: ?Sized> Deref for OptimisticLockCouplingWriteGuard<'_, T> {
+unsafe impl<T: ?Sized + Sync, W: LockWord> Sync for OptimisticLockCouplingWriteGuard<'_, T, W> {}
+impl<T: ?Sized, W: LockWord> Deref for OptimisticLockCouplingWriteGuard<'_, T, W> {
     type Target = T;
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
         unsafe { &*self.lock.data.get() }
     }
 }
-impl<T: ?Sized> DerefMut for OptimisticLockCouplingWriteGuard<'_, T> {
+impl<T: ?Sized, W: LockWord> DerefMut for OptimisticLockCouplingWriteGuard<'_, T, W> {
     #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { &mut *self.lock.data.get() }
     }
 }
-impl<T: ?Sized> Drop for OptimisticLockCouplingWriteGuard<'_, T> {
+impl<T: ?Sized, W: LockWord> Drop for OptimisticLockCouplingWriteGuard<'_, T, W> {
     #[inline(always)]
     fn drop(&mut self) {
-        if std::thread::panicking() {
-            self.lock.poisoned.fetch_or(true, Release);
-        } else {
-            self.lock.version_lock_outdate.fetch_add(0b10, Release);
-        }
+        // poison and release in one step, whoever sees the new version sees the poison too
+        let mark = if panicking() { W::POISON } else { W::ZERO };
+        self.lock.unlock(mark);
     }
 }
-impl<T: Debug> Debug for OptimisticLockCouplingWriteGuard<'_, T> {
+impl<T: Debug, W: LockWord> Debug for OptimisticLockCouplingWriteGuard<'_, T, W> {
     #[inline(always)]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("OptimisticLockCouplingWriteGuard")
             .field(
                 "version",
-                &(self.lock.version_lock_outdate.load(Relaxed) >> 2),
+                &version_of(W::load(&self.lock.version_lock_outdate, Relaxed)),
             )
             .field("data", self.deref())
             .finish()
     }
 }
-impl<T: Debug + Display> Display for OptimisticLockCouplingWriteGuard<'_, T> {
+impl<T: Debug + Display, W: LockWord> Display for OptimisticLockCouplingWriteGuard<'_, T, W> {
     #[inline(always)]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_fmt(format_args!(
             "OptimisticLockCouplingWriteGuard (ver: {}) {}",
-            self.lock.version_lock_outdate.load(Relaxed) >> 2,
+            version_of(W::load(&self.lock.version_lock_outdate, Relaxed)),
             self.deref()
         ))
     }
 }
-impl<'a, T: ?Sized> OptimisticLockCouplingWriteGuard<'a, T> {
+impl<'a, T: ?Sized, W: LockWord> OptimisticLockCouplingWriteGuard<'a, T, W> {
+    /// the caller must hold the lock
     #[inline]
-    pub const fn new(lock: &'a OptimisticLockCoupling<T>) -> Self {
+    pub(crate) const fn new(lock: &'a OptimisticLockCoupling<T, W>) -> Self {
         Self { lock }
     }
+    /// Consume self, release the lock and keep reading from the version you just wrote
+    /// no other writer can sneak in between
+    ///
+    /// # Safety
+    /// the result is a raw read guard, same rules as `OptimisticLockCoupling::read`
+    #[inline(always)]
+    pub unsafe fn downgrade(self) -> OptimisticLockCouplingReadGuard<'a, T, W> {
+        let lock = self.lock;
+        // we are doing the release here, skip the `Drop`
+        core::mem::forget(self);
+        let version = lock.unlock(W::ZERO);
+        OptimisticLockCouplingReadGuard {
+            lock,
+            version,
+            _not_send: PhantomData,
+        }
+    }
+    /// Consume self, release the lock and mark the data outdated in one atomic step
+    /// the paper's `writeUnlockObsolete`, use it when the node was replaced or removed
+    /// no reader can see it unlocked but not yet outdated
+    #[inline(always)]
+    pub fn unlock_obsolete(self) {
+        let lock = self.lock;
+        core::mem::forget(self);
+        let mark = if panicking() { W::POISON } else { W::ZERO };
+        lock.unlock(W::OUTDATE | mark);
+    }
+}
+
+// ============= mapped writer guard =============== //
+
+/// A write guard that only reaches a part of the data, made by `OptimisticLockCouplingWriteGuard::map`
+/// the lock stays held, dropping it bumps the version and poisons on panic like the write guard
+pub struct OptimisticLockCouplingMappedWriteGuard<'a, U: ?Sized + 'a, W: LockWord = DefaultLockWord>
+{
+    word: &'a W::Atomic,
+    data: *mut U,
+    /// borrows the data mutably like the write guard does
+    _lock: PhantomData<&'a mut U>,
+}
+unsafe impl<U: ?Sized + Send, W: LockWord> Send
+    for OptimisticLockCouplingMappedWriteGuard<'_, U, W>
+{
+}
+unsafe impl<U: ?Sized + Sync, W: LockWord> Sync
+    for OptimisticLockCouplingMappedWriteGuard<'_, U, W>
+{
+}
+impl<'a, T: ?Sized, W: LockWord> OptimisticLockCouplingWriteGuard<'a, T, W> {
+    /// Consume the guard and keep only the part `f` picks, the lock stays held
+    /// an associated function so it doesn't hide a `map` of `T`
+    #[inline(always)]
+    pub fn map<U, F>(mut guard: Self, f: F) -> OptimisticLockCouplingMappedWriteGuard<'a, U, W>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let word = &guard.lock.version_lock_outdate;
+        // a panic in `f` drops the guard and poisons as usual
+        let data: *mut U = f(&mut guard);
+        // the mapped guard does the release now, skip the `Drop`
+        core::mem::forget(guard);
+        OptimisticLockCouplingMappedWriteGuard {
+            word,
+            data,
+            _lock: PhantomData,
+        }
+    }
+}
+impl<'a, U: ?Sized, W: LockWord> OptimisticLockCouplingMappedWriteGuard<'a, U, W> {
+    /// narrow it down further
+    #[inline(always)]
+    pub fn map<V, F>(mut guard: Self, f: F) -> OptimisticLockCouplingMappedWriteGuard<'a, V, W>
+    where
+        V: ?Sized,
+        F: FnOnce(&mut U) -> &mut V,
+    {
+        let word = guard.word;
+        let data: *mut V = f(&mut guard);
+        core::mem::forget(guard);
+        OptimisticLockCouplingMappedWriteGuard {
+            word,
+            data,
+            _lock: PhantomData,
+        }
+    }
+}
+impl<U: ?Sized, W: LockWord> Deref for OptimisticLockCouplingMappedWriteGuard<'_, U, W> {
+    type Target = U;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.data }
+    }
+}
+impl<U: ?Sized, W: LockWord> DerefMut for OptimisticLockCouplingMappedWriteGuard<'_, U, W> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.data }
+    }
+}
+impl<U: ?Sized, W: LockWord> Drop for OptimisticLockCouplingMappedWriteGuard<'_, U, W> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let mark = if panicking() { W::POISON } else { W::ZERO };
+        unlock_word(self.word, mark);
+    }
+}
+impl<U: Debug + ?Sized, W: LockWord> Debug for OptimisticLockCouplingMappedWriteGuard<'_, U, W> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OptimisticLockCouplingMappedWriteGuard")
+            .field("version", &version_of(W::load(self.word, Relaxed)))
+            .field("data", &self.deref())
+            .finish()
+    }
 }
@@ -0,0 +1,32 @@
+//! Types `load` can copy a byte at a time.
+//!
+//! The copy in `load` reads every byte of the data with an atomic load, so none of them
+//! may be uninitialized, padding included. Reading padding as a `u8` is undefined behaviour
+//! even if the value is thrown away afterwards.
+
+/// Every byte of the type is initialized, whatever value it holds
+///
+/// implemented for the integers, floats, `bool`, `char` and arrays of them.
+/// A `#[repr(C)]` or `#[repr(transparent)]` struct whose fields fill it without gaps can opt in too.
+///
+/// # Safety
+/// the type must have no padding and no other possibly uninitialized bytes,
+/// so no `MaybeUninit`, unions or enums with payloads of different sizes
+///
+/// ```compile_fail
+/// use optimistic_lock_coupling::OptimisticLockCoupling;
+///
+/// // 7 bytes of padding after the `u8`
+/// let lock = OptimisticLockCoupling::new((1u8, 2u64));
+/// lock.load();
+/// ```
+pub unsafe trait NoUninit: Copy {}
+
+macro_rules! no_uninit {
+    ($($t:ty),*) => {
+        $(unsafe impl NoUninit for $t {})*
+    };
+}
+no_uninit!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+no_uninit!(f32, f64, bool, char, ());
+unsafe impl<T: NoUninit, const N: usize> NoUninit for [T; N] {}
@@ -0,0 +1,130 @@
+//! Where blocked threads sleep until the writer leaves.
+//!
+//! Locks don't carry a queue of their own, that would make every node fat.
+//! Instead waiters hash the address of the lock word into a small global table,
+//! and the writer only touches the table when somebody is actually waiting in its bucket.
+use std::{
+    sync::{
+        atomic::{fence, AtomicUsize, Ordering::*},
+        Condvar, Mutex, PoisonError,
+    },
+    task::Waker,
+    time::Instant,
+    vec::Vec,
+};
+
+const BUCKET_BITS: u32 = 6;
+const BUCKET_COUNT: usize = 1 << BUCKET_BITS;
+
+struct Bucket {
+    /// threads sleeping (or about to sleep) and tasks registered in this bucket
+    waiters: AtomicUsize,
+    /// wakers of the tasks waiting in this bucket
+    wakers: Mutex<Vec<Waker>>,
+    condvar: Condvar,
+}
+impl Bucket {
+    const fn new() -> Self {
+        Self {
+            waiters: AtomicUsize::new(0),
+            wakers: Mutex::new(Vec::new()),
+            condvar: Condvar::new(),
+        }
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_BUCKET: Bucket = Bucket::new();
+static BUCKETS: [Bucket; BUCKET_COUNT] = [EMPTY_BUCKET; BUCKET_COUNT];
+
+#[inline(always)]
+fn bucket(addr: usize) -> &'static Bucket {
+    // fibonacci hashing, the low bits of an address are mostly alignment
+    let hash = addr.wrapping_mul(0x9E37_79B9_7F4A_7C15_u64 as usize);
+    &BUCKETS[hash >> (usize::BITS - BUCKET_BITS)]
+}
+
+/// sleep until `blocked` says the lock at `addr` is free
+/// `blocked` must read the lock word, it is checked again after every wake up
+pub(crate) fn park(addr: usize, blocked: impl Fn() -> bool) {
+    let bucket = bucket(addr);
+    let mut guard = bucket.wakers.lock().unwrap_or_else(PoisonError::into_inner);
+    bucket.waiters.fetch_add(1, Relaxed);
+    // pairs with the `SeqCst` release before `unpark_all`: either we see the lock released
+    // or the writer sees us waiting
+    fence(SeqCst);
+    while blocked() {
+        guard = bucket
+            .condvar
+            .wait(guard)
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+    bucket.waiters.fetch_sub(1, Relaxed);
+}
+
+/// like `park` but gives up at `deadline`
+/// returns false if the lock was still blocked when the deadline passed
+pub(crate) fn park_until(addr: usize, blocked: impl Fn() -> bool, deadline: Instant) -> bool {
+    let bucket = bucket(addr);
+    let mut guard = bucket.wakers.lock().unwrap_or_else(PoisonError::into_inner);
+    bucket.waiters.fetch_add(1, Relaxed);
+    fence(SeqCst);
+    let mut free = true;
+    while blocked() {
+        let now = Instant::now();
+        if now >= deadline {
+            free = false;
+            break;
+        }
+        guard = bucket
+            .condvar
+            .wait_timeout(guard, deadline - now)
+            .unwrap_or_else(PoisonError::into_inner)
+            .0;
+    }
+    bucket.waiters.fetch_sub(1, Relaxed);
+    free
+}
+
+/// the async version of `park`, leaves `waker` behind instead of sleeping
+/// returns false if the lock is already free and the caller should retry at once
+///
+/// a task that stops waiting leaves a stale waker here,
+/// it is woken and thrown away by the next release in the bucket
+pub(crate) fn register(addr: usize, waker: &Waker, blocked: impl Fn() -> bool) -> bool {
+    let bucket = bucket(addr);
+    let mut wakers = bucket.wakers.lock().unwrap_or_else(PoisonError::into_inner);
+    bucket.waiters.fetch_add(1, Relaxed);
+    fence(SeqCst);
+    if !blocked() {
+        bucket.waiters.fetch_sub(1, Relaxed);
+        return false;
+    }
+    // every waker in the bucket is woken on release, once is enough
+    if wakers.iter().any(|w| w.will_wake(waker)) {
+        bucket.waiters.fetch_sub(1, Relaxed);
+    } else {
+        wakers.push(waker.clone());
+    }
+    true
+}
+
+/// wake everyone waiting on the lock at `addr`
+/// call it after the lock word is released with a `SeqCst` read-modify-write,
+/// that is what orders the release before the check, a writer nobody waits for pays no fence
+#[inline(always)]
+pub(crate) fn unpark_all(addr: usize) {
+    let bucket = bucket(addr);
+    if bucket.waiters.load(SeqCst) != 0 {
+        let wakers = {
+            let mut wakers = bucket.wakers.lock().unwrap_or_else(PoisonError::into_inner);
+            bucket.condvar.notify_all();
+            bucket.waiters.fetch_sub(wakers.len(), Relaxed);
+            std::mem::take(&mut *wakers)
+        };
+        // wake outside of the mutex, wakers may run arbitrary code
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
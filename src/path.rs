@@ -0,0 +1,78 @@
+//! The way down a tree, kept so a failed descent doesn't have to start over.
+//!
+//! `read_path_txn` hands an `OlcPath` to its logic, which descends one node at a time.
+//! Every node stays on the path with the version it was read at, when the attempt fails
+//! the path is validated from the bottom up and the next attempt starts from the deepest
+//! node that didn't change, instead of the root.
+use alloc::vec::Vec;
+
+use crate::{
+    DefaultLockWord, LockWord, OptimisticLockCoupling, OptimisticLockCouplingReadGuard,
+    OptimisticLockCouplingResult,
+};
+
+/// The read guards of a root-to-leaf descent, see `OptimisticLockCoupling::read_path_txn`
+/// all nodes share one type, the way nodes of a tree do
+pub struct OlcPath<'a, T: ?Sized + 'a, W: LockWord = DefaultLockWord> {
+    guards: Vec<OptimisticLockCouplingReadGuard<'a, T, W>>,
+}
+impl<'a, T: ?Sized, W: LockWord> OlcPath<'a, T, W> {
+    #[inline(always)]
+    pub(crate) fn new() -> Self {
+        Self { guards: Vec::new() }
+    }
+    /// the node the descent got to, the root if it just (re)started
+    #[inline(always)]
+    pub fn last(&self) -> &OptimisticLockCouplingReadGuard<'a, T, W> {
+        self.guards
+            .last()
+            .expect("an OlcPath always holds the root")
+    }
+    /// how many nodes are on the path, the root counts
+    #[inline(always)]
+    pub fn depth(&self) -> usize {
+        self.guards.len()
+    }
+    /// go one level down, `child` picks the child lock from the last node
+    /// the same protocol as `couple`, but the parent stays on the path
+    #[inline(always)]
+    pub fn descend<F>(&mut self, child: F) -> OptimisticLockCouplingResult<()>
+    where
+        F: FnOnce(&'a T) -> &'a OptimisticLockCoupling<T, W>,
+    {
+        let parent = self.last();
+        // SAFETY: same as `Deref`, the pointer may be torn until the parent says otherwise
+        let child = child(unsafe { &*parent.lock.data.get() });
+        parent.check()?;
+        let child = OptimisticLockCouplingReadGuard::new(child)?;
+        // the child was still linked when its version was read
+        parent.check()?;
+        self.guards.push(child);
+        Ok(())
+    }
+    /// validate every node on the path, bottom up
+    #[inline(always)]
+    pub fn check(&self) -> OptimisticLockCouplingResult<()> {
+        self.guards.iter().rev().try_for_each(|g| g.check())
+    }
+    /// drop the nodes that changed since they were read, and everything below them
+    /// a node below a changed one may not be linked anymore, even if it is still valid itself
+    #[inline(always)]
+    pub(crate) fn restart(&mut self) {
+        let mut valid = self.guards.len();
+        for (depth, guard) in self.guards.iter().enumerate().rev() {
+            if guard.check().is_err() {
+                valid = depth;
+            }
+        }
+        self.guards.truncate(valid);
+    }
+    #[inline(always)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.guards.is_empty()
+    }
+    #[inline(always)]
+    pub(crate) fn push(&mut self, guard: OptimisticLockCouplingReadGuard<'a, T, W>) {
+        self.guards.push(guard);
+    }
+}
@@ -0,0 +1,118 @@
+//! Versions of many locks, validated together.
+//!
+//! Each lock on its own only says its data didn't change while it was read.
+//! If every version in the set still holds at `validate`, they all held at the moment
+//! the first one was re-checked, so the reads form one snapshot across the locks.
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering::*;
+
+use crate::{
+    sync::fence, try_lock_word, Backoff, DefaultLockWord, ExponentialBackoff, LockWord,
+    OptimisticLockCoupling, OptimisticLockCouplingErrorType, OptimisticLockCouplingReadGuard,
+    OptimisticLockCouplingResult,
+};
+
+/// `(lock, version)` pairs of locks that may all hold different `T`s
+/// only the word of each lock is kept, the data is never touched
+pub struct ReadSet<'a, W: LockWord = DefaultLockWord> {
+    pub(crate) entries: Vec<(&'a W::Atomic, W)>,
+}
+impl<W: LockWord> Default for ReadSet<'_, W> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+impl<'a, W: LockWord> ReadSet<'a, W> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// read `lock` once, `logic` sees the data the same way it does in `read_with`
+    /// the version goes into the set, so the result is part of the snapshot once `validate` passes
+    #[inline(always)]
+    pub fn read<T, F, R>(
+        &mut self,
+        lock: &'a OptimisticLockCoupling<T, W>,
+        logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        T: ?Sized,
+        F: FnOnce(&T) -> R,
+    {
+        // SAFETY: the result is only handed out after `check`
+        let guard = unsafe { lock.read() }?;
+        let r = logic(&guard);
+        guard.check()?;
+        self.add(&guard);
+        Ok(r)
+    }
+    /// put the version a raw guard was read at into the set
+    #[inline(always)]
+    pub fn add<T: ?Sized>(&mut self, guard: &OptimisticLockCouplingReadGuard<'a, T, W>) {
+        self.entries
+            .push((&guard.lock.version_lock_outdate, guard.version));
+    }
+    /// is every lock still at the version it was read at?
+    pub fn validate(&self) -> OptimisticLockCouplingResult<()> {
+        // one fence covers the reads of all the locks
+        fence(Acquire);
+        for &(word, version) in &self.entries {
+            if try_lock_word::<W>(word)? != version {
+                return Err(OptimisticLockCouplingErrorType::VersionUpdated);
+            }
+        }
+        Ok(())
+    }
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// forget every version, to start a new snapshot
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+    /// read transaction over many locks
+    /// logic fills a fresh set every attempt, the set is validated before `r` is returned
+    /// only `Poisoned`, `Outdated` and `TimedOut` are returned
+    /// waits with the default `ExponentialBackoff` between retries
+    #[inline(always)]
+    pub fn txn<F, R>(logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnMut(&mut Self) -> OptimisticLockCouplingResult<R>,
+    {
+        Self::txn_with(ExponentialBackoff::default(), logic)
+    }
+    /// `txn` with your own `Backoff` between retries
+    pub fn txn_with<B, F, R>(mut backoff: B, mut logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        B: Backoff,
+        F: FnMut(&mut Self) -> OptimisticLockCouplingResult<R>,
+    {
+        use OptimisticLockCouplingErrorType::*;
+        backoff.reset();
+        let mut set = Self::new();
+        'txn: loop {
+            set.clear();
+            match logic(&mut set).and_then(|r| set.validate().map(|_| r)) {
+                Ok(r) => {
+                    return Ok(r);
+                }
+                Err(e @ (Poisoned | Outdated | TimedOut)) => {
+                    return Err(e);
+                }
+                Err(_) => {
+                    backoff.backoff();
+                    continue 'txn;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! The atomics behind the lock word.
+//!
+//! Build with `RUSTFLAGS="--cfg loom"` and they are swapped for `loom`'s,
+//! so the tests in `test_loom.rs` can check every interleaving of read, write and outdate.
+#[cfg(all(loom, target_has_atomic = "64"))]
+pub(crate) use loom::sync::atomic::AtomicU64;
+#[cfg(loom)]
+pub(crate) use loom::{
+    hint::spin_loop,
+    sync::atomic::{fence, AtomicU32},
+};
+
+#[cfg(not(loom))]
+pub(crate) use core::{
+    hint::spin_loop,
+    sync::atomic::{fence, AtomicU32},
+};
+// thumbv7m, riscv32imac and friends have no 64 bit atomics
+#[cfg(all(not(loom), target_has_atomic = "64"))]
+pub(crate) use core::sync::atomic::AtomicU64;
+
+#[cfg(all(loom, feature = "std"))]
+pub(crate) use loom::thread::yield_now;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::thread::yield_now;
+
+/// a `const fn` everywhere but under loom, whose atomics can't be built in a const context
+macro_rules! const_fn {
+    ($(#[$meta:meta])* $vis:vis fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$meta])*
+        $vis const fn $($rest)*
+        #[cfg(loom)]
+        $(#[$meta])*
+        $vis fn $($rest)*
+    };
+}
+pub(crate) use const_fn;
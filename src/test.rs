@@ -1,8 +1,10 @@
 use super::*;
+use std::{println, vec::Vec};
+use word::sealed::Sealed;
 #[test]
 fn read_lock() {
     let lock = OptimisticLockCoupling::new(1);
-    let r = lock.read().and_then(|r| {
+    let r = unsafe { lock.read() }.and_then(|r| {
         println!("{}", r);
         assert_eq!(*r, 1);
         r.try_sync()
@@ -12,27 +14,55 @@ fn read_lock() {
 #[test]
 fn write_lock() {
     let lock = OptimisticLockCoupling::new(1);
-    let _w = lock.write().and_then(|mut w| {
+    let _w = lock.write().map(|mut w| {
         *w += 1;
-        Ok(())
     });
     assert_eq!(lock.version_lock_outdate.load(Acquire), 0b100);
-    let _r = lock.read().and_then(|r| {
+    let _r = unsafe { lock.read() }.and_then(|r| {
         println!("{}", r);
         assert_eq!(*r, 2);
         r.try_sync()
     });
 }
 #[test]
+fn upgrade_read_to_write() {
+    let lock = OptimisticLockCoupling::new(1);
+    let r = unsafe { lock.read() }.unwrap();
+    let mut w = r.upgrade().unwrap();
+    *w += 1;
+    drop(w);
+    assert_eq!(lock.version_lock_outdate.load(Acquire), 0b100);
+
+    let r = unsafe { lock.read() }.unwrap();
+    *lock.write().unwrap() += 1;
+    assert_eq!(
+        r.upgrade().err(),
+        Some(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+}
+#[test]
+fn downgrade_write_to_read() {
+    let lock = OptimisticLockCoupling::new(1);
+    let mut w = lock.write().unwrap();
+    *w += 1;
+    let r = unsafe { w.downgrade() };
+    assert_eq!(*r, 2);
+    assert_eq!(lock.version_lock_outdate.load(Acquire), 0b100);
+    // the lock is released so others can read
+    lock.read_with(|_| ()).unwrap();
+    r.try_sync().unwrap();
+}
+#[test]
 #[should_panic]
 fn read_while_write() {
     let lock = OptimisticLockCoupling::new(1);
     let _w = lock.write().unwrap();
     // will fail due to its blocked
-    let _r = lock.read().unwrap();
+    let _r = unsafe { lock.read() }.unwrap();
 }
 
 #[test]
+#[allow(static_mut_refs)]
 fn lots_thread_write() {
     static mut LOCK: Option<OptimisticLockCoupling<i32>> = None;
     unsafe { LOCK = Some(OptimisticLockCoupling::from(0)) };
@@ -41,7 +71,7 @@ fn lots_thread_write() {
         for _ in 0..10000 {
             loop {
                 unsafe {
-                    match (&LOCK).as_ref().unwrap().write() {
+                    match LOCK.as_ref().unwrap().write() {
                         Ok(mut guard) => {
                             *guard += 1;
                             break;
@@ -67,3 +97,748 @@ fn lots_thread_write() {
         read.try_sync().unwrap();
     }
 }
+
+#[test]
+fn lots_thread_write_txn() {
+    let lock = std::sync::Arc::new(OptimisticLockCoupling::new(0));
+    let threads: Vec<_> = (0..3)
+        .map(|_| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10000 {
+                    lock.write_txn(|guard| **guard += 1).unwrap();
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(lock.write_txn(|guard| **guard), Ok(30000));
+}
+
+#[test]
+fn txn_with_backoff() {
+    // releases the writer on the first retry
+    struct Release<'a>(Option<OptimisticLockCouplingWriteGuard<'a, i32>>, usize);
+    impl Backoff for Release<'_> {
+        fn backoff(&mut self) {
+            self.0.take();
+            self.1 += 1;
+        }
+    }
+    let lock = OptimisticLockCoupling::new(1);
+    let mut release = Release(Some(lock.write().unwrap()), 0);
+    assert_eq!(lock.read_txn_with(&mut release, |g| Ok(**g)), Ok(1));
+    assert_eq!(release.1, 1);
+
+    let mut release = Release(Some(lock.write().unwrap()), 0);
+    assert_eq!(lock.write_txn_with(&mut release, |g| **g += 1), Ok(()));
+    assert_eq!(release.1, 1);
+    assert_eq!(lock.read_txn_with(SpinBackoff, |g| Ok(**g)), Ok(2));
+
+    let mut release = Release(Some(lock.write().unwrap()), 0);
+    assert_eq!(lock.load_with(&mut release), Ok(2));
+    assert_eq!(release.1, 1);
+
+    // a write inside the first attempt makes it retry
+    #[cfg(feature = "std")]
+    {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let mut release = Release(None, 0);
+        let mut first = true;
+        let res = lock.read_txn_until_with(deadline, &mut release, |g| {
+            if core::mem::take(&mut first) {
+                *lock.write().unwrap() += 1;
+            }
+            Ok(**g)
+        });
+        assert_eq!(res, Ok(3));
+        assert_eq!(release.1, 1);
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn blocking_acquisition() {
+    let lock = std::sync::Arc::new(OptimisticLockCoupling::new(0));
+    let mut w = lock.write().unwrap();
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                *lock.write_blocking().unwrap() += 1;
+                let r = unsafe { lock.read_blocking() }.unwrap();
+                assert!(*r >= 2);
+                r.try_sync().ok();
+            })
+        })
+        .collect();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    *w += 1;
+    drop(w);
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(lock.write_txn(|g| **g), Ok(3));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn blocking_wakes_on_poison() {
+    let lock = std::sync::Arc::new(OptimisticLockCoupling::new(0));
+    let (tx, rx) = std::sync::mpsc::channel();
+    let writer = {
+        let lock = lock.clone();
+        std::thread::spawn(move || {
+            let _w = lock.write().unwrap();
+            tx.send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            panic!("writer died");
+        })
+    };
+    rx.recv().unwrap();
+    assert_eq!(
+        unsafe { lock.read_blocking() }.err(),
+        Some(OptimisticLockCouplingErrorType::Poisoned)
+    );
+    assert!(writer.join().is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn timed_acquisition() {
+    use std::time::{Duration, Instant};
+    let lock = std::sync::Arc::new(OptimisticLockCoupling::new(0));
+    let w = lock.write().unwrap();
+    assert_eq!(
+        lock.try_write_for(Duration::from_millis(20)).err(),
+        Some(OptimisticLockCouplingErrorType::TimedOut)
+    );
+    assert_eq!(
+        lock.read_txn_until(Instant::now() + Duration::from_millis(20), |g| Ok(**g)),
+        Err(OptimisticLockCouplingErrorType::TimedOut)
+    );
+    let waiter = {
+        let lock = lock.clone();
+        std::thread::spawn(move || {
+            *lock.try_write_for(Duration::from_secs(10)).unwrap() += 1;
+            lock.read_txn_until(Instant::now() + Duration::from_secs(10), |g| Ok(**g))
+        })
+    };
+    std::thread::sleep(Duration::from_millis(50));
+    drop(w);
+    assert_eq!(waiter.join().unwrap(), Ok(1));
+    // no deadline fits, waits like `write_blocking`
+    *lock.try_write_for(Duration::MAX).unwrap() += 1;
+    assert_eq!(lock.read_with(|v| *v), Ok(2));
+}
+
+/// the smallest executor, parks the thread until woken
+#[cfg(feature = "std")]
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    use std::task::{Context, Poll, Wake, Waker};
+    struct ThreadWaker(std::thread::Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: std::sync::Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let mut fut = std::boxed::Box::pin(fut);
+    let waker = Waker::from(std::sync::Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(r) => return r,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn async_acquisition() {
+    fn assert_send<F: Send>(f: F) -> F {
+        f
+    }
+    let lock = std::sync::Arc::new(OptimisticLockCoupling::new(0));
+    let w = lock.write().unwrap();
+    let task = {
+        let lock = lock.clone();
+        std::thread::spawn(move || {
+            block_on(assert_send(async {
+                *lock.write_async().await.unwrap() += 1;
+                lock.read_txn_async(|g| Ok(**g)).await
+            }))
+        })
+    };
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    drop(w);
+    assert_eq!(task.join().unwrap(), Ok(1));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn async_cancellation() {
+    use std::future::Future;
+    use std::task::{Context, Waker};
+    let lock = OptimisticLockCoupling::new(0);
+    let w = lock.write().unwrap();
+    {
+        let mut fut = std::boxed::Box::pin(lock.write_async());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        // dropped while waiting
+    }
+    drop(w);
+    assert_eq!(block_on(lock.read_txn_async(|g| Ok(**g))), Ok(0));
+    assert_eq!(lock.write_txn(|g| **g), Ok(0));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn poison_recovery() {
+    let lock = std::sync::Arc::new(OptimisticLockCoupling::new(0));
+    let writer = {
+        let lock = lock.clone();
+        std::thread::spawn(move || {
+            let mut w = lock.write().unwrap();
+            *w = -1;
+            panic!("writer died");
+        })
+    };
+    assert!(writer.join().is_err());
+    assert!(lock.is_poisoned());
+    assert_eq!(
+        lock.read_with(|_| ()).err(),
+        Some(OptimisticLockCouplingErrorType::Poisoned)
+    );
+    assert_eq!(
+        lock.write().err(),
+        Some(OptimisticLockCouplingErrorType::Poisoned)
+    );
+    // the lock bit is released, so the data can be repaired
+    match lock.try_write() {
+        Err(OptimisticLockCouplingTryLockError::Poisoned(e)) => {
+            let mut w = e.into_inner();
+            assert_eq!(*w, -1);
+            *w = 0;
+        }
+        _ => unreachable!(),
+    }
+    *lock.write_ignore_poison().unwrap() += 1;
+    lock.clear_poison();
+    assert_eq!(lock.read_txn(|g| Ok(**g)), Ok(1));
+    assert!(lock.try_write().is_ok());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn try_lock_error_needs_no_debug_data() {
+    use std::string::ToString;
+    struct Opaque(i32);
+    let lock = OptimisticLockCoupling::new(Opaque(1));
+    lock.try_write().unwrap().0 += 1;
+    let _w = lock.write().unwrap();
+    let e = lock.try_write().err().unwrap();
+    assert_eq!(std::format!("{:?}", e), "Failed(Blocked)");
+    assert_eq!(e.to_string(), "the lock could not be acquired: Blocked");
+    let e: &dyn std::error::Error = &e;
+    assert!(e.source().is_none());
+}
+
+#[test]
+fn load_is_never_torn() {
+    let lock = std::sync::Arc::new(OptimisticLockCoupling::new([0u64; 16]));
+    let writer = {
+        let lock = lock.clone();
+        std::thread::spawn(move || {
+            for i in 1..=10000 {
+                lock.write_txn(|g| **g = [i; 16]).unwrap();
+            }
+        })
+    };
+    let mut last = 0;
+    while last != 10000 {
+        let snapshot = lock.load().unwrap();
+        assert!(snapshot.iter().all(|x| *x == snapshot[0]));
+        assert!(snapshot[0] >= last);
+        last = snapshot[0];
+    }
+    writer.join().unwrap();
+}
+
+#[test]
+fn load_padded_data() {
+    // `(u32, u64)` would have 4 bytes of padding, spell them out and it can opt in
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(C)]
+    struct Entry {
+        key: u32,
+        _pad: u32,
+        value: u64,
+    }
+    unsafe impl NoUninit for Entry {}
+    let entry = Entry {
+        key: 1,
+        _pad: 0,
+        value: 2,
+    };
+    let lock = OptimisticLockCoupling::new(entry);
+    assert_eq!(lock.load(), Ok(entry));
+    lock.write().unwrap().value += 1;
+    assert_eq!(lock.load().map(|e| e.value), Ok(3));
+}
+
+#[test]
+fn read_with_closure() {
+    let lock = OptimisticLockCoupling::new(std::vec![1, 2, 3]);
+    assert_eq!(lock.read_with(|v| v.iter().sum::<i32>()), Ok(6));
+    let w = lock.write().unwrap();
+    assert_eq!(
+        lock.read_with(|v| v.len()),
+        Err(OptimisticLockCouplingErrorType::Blocked)
+    );
+    drop(w);
+    assert_eq!(lock.read_with(|v| v.len()), Ok(3));
+}
+
+#[test]
+fn version_overflow_outdates() {
+    let lock = OptimisticLockCoupling::with_overflow_policy(1, VersionOverflowPolicy::Outdate);
+    assert_eq!(lock.overflow_policy(), VersionOverflowPolicy::Outdate);
+    lock.version_lock_outdate
+        .fetch_or(u64::MAX_VERSION << 2, Relaxed);
+    // read right before the last write
+    let r = unsafe { lock.read() }.unwrap();
+    *lock.write().unwrap() += 1;
+    assert_eq!(r.try_sync(), Err(OptimisticLockCouplingErrorType::Outdated));
+    assert_eq!(
+        lock.read_with(|_| ()),
+        Err(OptimisticLockCouplingErrorType::Outdated)
+    );
+    assert_eq!(
+        lock.write().err(),
+        Some(OptimisticLockCouplingErrorType::Outdated)
+    );
+    // the version did not wrap, the policy bit is untouched
+    let word = lock.version_lock_outdate.load(Relaxed);
+    assert_eq!(version_of(word), u64::MAX_VERSION);
+    assert_eq!(lock.overflow_policy(), VersionOverflowPolicy::Outdate);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn version_overflow_panics() {
+    let lock = OptimisticLockCoupling::new(1);
+    assert_eq!(lock.overflow_policy(), VersionOverflowPolicy::Panic);
+    lock.version_lock_outdate
+        .fetch_or(u64::MAX_VERSION << 2, Relaxed);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        *lock.write().unwrap() += 1;
+    }));
+    assert!(res.is_err());
+    assert!(!lock.is_poisoned());
+    assert_eq!(
+        lock.read_with(|_| ()),
+        Err(OptimisticLockCouplingErrorType::Outdated)
+    );
+}
+
+#[test]
+fn compact_lock_word() {
+    assert_eq!(core::mem::size_of::<OptimisticLockCoupling<u32, u32>>(), 8);
+    assert_eq!(core::mem::size_of::<OptimisticLockCoupling<u64>>(), 16);
+    let lock =
+        OptimisticLockCoupling::compact_with_overflow_policy(1, VersionOverflowPolicy::Outdate);
+    *lock.write().unwrap() += 1;
+    assert_eq!(
+        lock.version_lock_outdate.load(Acquire),
+        0b100 | u32::OUTDATE_ON_OVERFLOW
+    );
+    assert_eq!(lock.read_with(|v| *v), Ok(2));
+    // 28 bits run out fast, the policy still holds
+    lock.version_lock_outdate
+        .fetch_or(u32::MAX_VERSION << 2, Relaxed);
+    *lock.write().unwrap() += 1;
+    assert_eq!(
+        lock.read_with(|_| ()),
+        Err(OptimisticLockCouplingErrorType::Outdated)
+    );
+    assert_eq!(
+        version_of(lock.version_lock_outdate.load(Relaxed)),
+        u32::MAX_VERSION
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn compact_lock_poison() {
+    let lock = OptimisticLockCoupling::new_compact(0);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _w = lock.write().unwrap();
+        panic!("writer died");
+    }));
+    assert!(res.is_err());
+    // poison and release landed in the same word
+    assert_eq!(lock.version_lock_outdate.load(Acquire), 0b100 | u32::POISON);
+    assert_eq!(
+        lock.read_with(|_| ()),
+        Err(OptimisticLockCouplingErrorType::Poisoned)
+    );
+    lock.clear_poison();
+    assert_eq!(lock.read_txn(|g| Ok(**g)), Ok(0));
+}
+
+#[test]
+fn unlock_obsolete() {
+    let lock = OptimisticLockCoupling::new(1);
+    let r = unsafe { lock.read() }.unwrap();
+    let mut w = lock.write().unwrap();
+    *w += 1;
+    w.unlock_obsolete();
+    // unlocked and outdated in the same word
+    assert_eq!(lock.version_lock_outdate.load(Acquire), 0b101);
+    assert_eq!(r.try_sync(), Err(OptimisticLockCouplingErrorType::Outdated));
+    assert_eq!(
+        lock.read_with(|_| ()),
+        Err(OptimisticLockCouplingErrorType::Outdated)
+    );
+    assert_eq!(
+        lock.write().err(),
+        Some(OptimisticLockCouplingErrorType::Outdated)
+    );
+}
+
+#[test]
+fn check_keeps_the_guard() {
+    let lock = OptimisticLockCoupling::new(std::vec![1, 2, 3]);
+    let r = unsafe { lock.read() }.unwrap();
+    let first = r[0];
+    assert_eq!(r.check(), Ok(()));
+    let last = r[2];
+    assert_eq!(r.check(), Ok(()));
+    assert_eq!(first + last, 4);
+    *lock.write().unwrap() = std::vec![0];
+    assert_eq!(
+        r.check(),
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+    assert_eq!(
+        r.try_sync(),
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+}
+
+#[test]
+fn couple_hand_over_hand() {
+    struct Node {
+        value: i32,
+        next: Option<std::boxed::Box<OptimisticLockCoupling<Node>>>,
+    }
+    let leaf = OptimisticLockCoupling::new(Node {
+        value: 3,
+        next: None,
+    });
+    let root = OptimisticLockCoupling::new(Node {
+        value: 1,
+        next: Some(std::boxed::Box::new(OptimisticLockCoupling::new(Node {
+            value: 2,
+            next: Some(std::boxed::Box::new(leaf)),
+        }))),
+    });
+    let sum = unsafe { root.read() }.and_then(|n1| {
+        let v1 = n1.value;
+        let n2 = n1.couple(|n| n.next.as_deref().unwrap())?;
+        let v2 = n2.value;
+        let n3 = n2.couple(|n| n.next.as_deref().unwrap())?;
+        let v3 = n3.value;
+        n3.try_sync().map(|_| v1 + v2 + v3)
+    });
+    assert_eq!(sum, Ok(6));
+    // the parent changed after it was read, the child is never handed out
+    let n1 = unsafe { root.read() }.unwrap();
+    root.write().unwrap().value += 1;
+    assert_eq!(
+        n1.couple(|n| n.next.as_deref().unwrap()).err(),
+        Some(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn path_resumes_from_deepest_valid_node() {
+    struct Node {
+        value: i32,
+        next: Option<std::boxed::Box<OptimisticLockCoupling<Node>>>,
+    }
+    let leaf = std::boxed::Box::new(OptimisticLockCoupling::new(Node {
+        value: 3,
+        next: None,
+    }));
+    let leaf_lock: *const OptimisticLockCoupling<Node> = &*leaf;
+    let root = OptimisticLockCoupling::new(Node {
+        value: 1,
+        next: Some(std::boxed::Box::new(OptimisticLockCoupling::new(Node {
+            value: 2,
+            next: Some(leaf),
+        }))),
+    });
+    let mut starts = Vec::new();
+    let sum = root.read_path_txn(|path| {
+        starts.push(path.depth());
+        while path.last().next.is_some() {
+            path.descend(|n| n.next.as_deref().unwrap())?;
+        }
+        if starts.len() == 1 {
+            // a writer gets to the leaf before the path is validated
+            unsafe { &*leaf_lock }.write().unwrap().value += 1;
+        }
+        Ok(path.last().value)
+    });
+    assert_eq!(sum, Ok(4));
+    // only the leaf was read again
+    assert_eq!(starts, [1, 2]);
+}
+
+#[test]
+fn mapped_read_guard() {
+    let lock = OptimisticLockCoupling::new((1, std::vec![2, 3]));
+    let r = unsafe { lock.read() }.unwrap();
+    let keys = OptimisticLockCouplingReadGuard::map(r, |t| &t.1);
+    assert_eq!(keys.len(), 2);
+    let last = OptimisticLockCouplingMappedReadGuard::map(keys, |k| &k[1]);
+    assert_eq!(*last, 3);
+    assert_eq!(last.try_sync(), Ok(()));
+    // a write to another field still fails the mapped guard
+    let r = unsafe { lock.read() }.unwrap();
+    let keys = OptimisticLockCouplingReadGuard::map(r, |t| &t.1);
+    lock.write().unwrap().0 += 1;
+    assert_eq!(
+        keys.check(),
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn mapped_write_guard() {
+    let lock = OptimisticLockCoupling::new((1, std::vec![2, 3]));
+    let r = unsafe { lock.read() }.unwrap();
+    {
+        let keys = OptimisticLockCouplingWriteGuard::map(lock.write().unwrap(), |t| &mut t.1);
+        let mut last = OptimisticLockCouplingMappedWriteGuard::map(keys, |k| &mut k[1]);
+        *last += 1;
+        assert_eq!(
+            lock.read_with(|_| ()),
+            Err(OptimisticLockCouplingErrorType::Blocked)
+        );
+    }
+    assert_eq!(lock.version_lock_outdate.load(Acquire), 0b100);
+    assert_eq!(
+        r.try_sync(),
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+    assert_eq!(lock.read_with(|t| t.1[1]), Ok(4));
+    // a panic while mapped still poisons
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut head = OptimisticLockCouplingWriteGuard::map(lock.write().unwrap(), |t| &mut t.0);
+        *head = -1;
+        panic!("writer died");
+    }));
+    assert!(res.is_err());
+    assert!(lock.is_poisoned());
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn arc_guards() {
+    use alloc::sync::Arc;
+    struct Holder {
+        guard: OptimisticLockCouplingArcWriteGuard<i32>,
+    }
+    fn take(lock: Arc<OptimisticLockCoupling<i32>>) -> Holder {
+        Holder {
+            guard: lock.write_arc().unwrap(),
+        }
+    }
+    let lock = Arc::new(OptimisticLockCoupling::new(1));
+    let r = unsafe { lock.read_arc() }.unwrap();
+    let mut holder = take(lock.clone());
+    *holder.guard += 1;
+    assert_eq!(
+        unsafe { lock.read_arc() }.err().unwrap(),
+        OptimisticLockCouplingErrorType::Blocked
+    );
+    let r2 = unsafe { holder.guard.downgrade() };
+    assert_eq!(
+        r.try_sync(),
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+    assert_eq!(*r2, 2);
+    let mut w = r2.upgrade().unwrap();
+    *w += 1;
+    drop(w);
+    assert_eq!(lock.read_with(|v| *v), Ok(3));
+    assert_eq!(Arc::strong_count(&lock), 1);
+}
+
+#[test]
+fn ownership() {
+    let mut lock = OptimisticLockCoupling::new(1);
+    *lock.get_mut().unwrap() += 1;
+    assert_eq!(unsafe { *lock.as_ptr() }, 2);
+    assert_eq!(lock.version_lock_outdate.load(Acquire), 0);
+    lock.version_lock_outdate.fetch_or(u64::POISON, Relaxed);
+    *lock.get_mut().unwrap_err().into_inner() += 1;
+    assert_eq!(lock.into_inner().unwrap_err().into_inner(), 3);
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn unsized_construction() {
+    use alloc::{boxed::Box, string::String};
+    let strings = std::vec![String::from("a"), String::from("b")];
+    let lock: Box<OptimisticLockCoupling<[String]>> = strings.into();
+    assert_eq!(lock.read_with(|s| s.concat()), Ok(String::from("ab")));
+    lock.write().unwrap()[1].push('c');
+    assert_eq!(lock.read_with(|s| s.concat()), Ok(String::from("abc")));
+    let compact: Box<OptimisticLockCoupling<[u8], u32>> = Box::<[u8]>::from([1, 2, 3]).into();
+    assert_eq!(core::mem::size_of_val(&*compact), 8);
+    assert_eq!(compact.read_with(|s| s.len()), Ok(3));
+    let empty: Box<OptimisticLockCoupling<[u64]>> = Vec::new().into();
+    assert_eq!(empty.read_with(|s| s.len()), Ok(0));
+    // unsizing coercions
+    let array: Box<OptimisticLockCoupling<[i32]>> = Box::new(OptimisticLockCoupling::new([1, 2]));
+    assert_eq!(array.read_with(|s| s.len()), Ok(2));
+    let shown: Box<OptimisticLockCoupling<dyn core::fmt::Display>> =
+        Box::new(OptimisticLockCoupling::new(7));
+    assert_eq!(
+        shown.read_with(|d| std::format!("{}", d)),
+        Ok(String::from("7"))
+    );
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn read_set_snapshot() {
+    let count = OptimisticLockCoupling::new(2);
+    let names = OptimisticLockCoupling::new(std::vec!["a", "b"]);
+    let mut set = ReadSet::new();
+    let n = set.read(&count, |c| *c).unwrap();
+    let len = set.read(&names, |v| v.len()).unwrap();
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.validate(), Ok(()));
+    assert_eq!(n, len);
+    // one of the locks moved on, the snapshot is gone
+    names.write().unwrap().push("c");
+    assert_eq!(
+        set.validate(),
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+    let mut first = true;
+    let res = ReadSet::txn(|set| {
+        let n = set.read(&count, |c| *c)?;
+        if first {
+            first = false;
+            *count.write().unwrap() += 1;
+        }
+        let len = set.read(&names, |v| v.len())?;
+        Ok((n, len))
+    });
+    assert_eq!(res, Ok((3, 3)));
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn transaction_commit_and_abort() {
+    let alice = OptimisticLockCoupling::new(100);
+    let bob = OptimisticLockCoupling::new(0);
+    let log = OptimisticLockCoupling::new(Vec::<&str>::new());
+    let transfer = |amount| {
+        Transaction::run(|txn| {
+            let balance = txn.read(&alice, |a| *a)?;
+            txn.write(&alice, balance - amount);
+            txn.update(&bob, |b| *b += amount)?;
+            txn.update(&log, |l| l.push("transfer"))?;
+            // reads see the buffered writes
+            txn.read(&alice, |a| *a)
+        })
+    };
+    assert_eq!(transfer(30), Ok(70));
+    assert_eq!(bob.read_with(|b| *b), Ok(30));
+    assert_eq!(log.read_with(|l| l.len()), Ok(1));
+    // a record read by the transaction changed before commit, nothing is installed
+    let mut txn = Transaction::new();
+    txn.update(&bob, |b| *b += 1).unwrap();
+    let before = alice.version_lock_outdate.load(Acquire);
+    assert_eq!(txn.read(&alice, |a| *a), Ok(70));
+    *alice.write().unwrap() += 1;
+    let alice_word = alice.version_lock_outdate.load(Acquire);
+    let bob_word = bob.version_lock_outdate.load(Acquire);
+    assert_eq!(
+        txn.commit(),
+        Err(OptimisticLockCouplingErrorType::VersionUpdated)
+    );
+    assert_ne!(before, alice_word);
+    // the abort left no trace, not even a version bump
+    assert_eq!(bob.version_lock_outdate.load(Acquire), bob_word);
+    assert_eq!(bob.read_with(|b| *b), Ok(30));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn transaction_commit_overflow_releases_every_record() {
+    let records = [
+        OptimisticLockCoupling::new(0),
+        OptimisticLockCoupling::new(0),
+    ];
+    for r in &records {
+        r.version_lock_outdate
+            .fetch_or(u64::MAX_VERSION << 2, Relaxed);
+    }
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut txn = Transaction::new();
+        txn.write(&records[0], 1);
+        txn.write(&records[1], 2);
+        txn.commit()
+    }));
+    assert!(res.is_err());
+    // the first release panicked, the other record was not left locked
+    for r in &records {
+        assert_eq!(r.version_lock_outdate.load(Acquire) & u64::LOCK, 0);
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn transactions_are_serializable() {
+    let accounts: std::sync::Arc<Vec<OptimisticLockCoupling<i64>>> =
+        std::sync::Arc::new((0..4).map(|_| OptimisticLockCoupling::new(100)).collect());
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let accounts = accounts.clone();
+            std::thread::spawn(move || {
+                for i in 0..1000 {
+                    let (from, to) = ((t + i) % 4, (t + i + 1) % 4);
+                    Transaction::run(|txn| {
+                        txn.update(&accounts[from], |a| *a -= 1)?;
+                        txn.update(&accounts[to], |a| *a += 1)
+                    })
+                    .unwrap();
+                    let total = Transaction::run(|txn| {
+                        let mut total = 0;
+                        for a in accounts.iter() {
+                            total += txn.read(a, |a| *a)?;
+                        }
+                        Ok(total)
+                    });
+                    assert_eq!(total, Ok(400));
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+}
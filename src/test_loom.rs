@@ -0,0 +1,100 @@
+//! Model checked with `RUSTFLAGS="--cfg loom" cargo test --lib --release`
+use super::*;
+use loom::{
+    sync::{atomic::AtomicU64 as Cell, Arc},
+    thread,
+};
+
+/// the payload is made of atomics too, so loom can reorder the data accesses
+type Pair = (Cell, Cell);
+fn pair() -> Arc<OptimisticLockCoupling<Pair>> {
+    Arc::new(OptimisticLockCoupling::new((Cell::new(0), Cell::new(0))))
+}
+
+#[test]
+fn torn_read_never_validates() {
+    loom::model(|| {
+        let lock = pair();
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                if let Ok(w) = lock.write() {
+                    w.0.store(1, Relaxed);
+                    w.1.store(1, Relaxed);
+                }
+            })
+        };
+        if let Ok((a, b)) = lock.read_with(|p| (p.0.load(Relaxed), p.1.load(Relaxed))) {
+            assert_eq!(a, b);
+        }
+        writer.join().unwrap();
+    });
+}
+
+#[test]
+fn load_never_returns_a_torn_copy() {
+    loom::model(|| {
+        let lock = Arc::new(OptimisticLockCoupling::new(0u64));
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                *lock.write().unwrap() = 1;
+            })
+        };
+        let seen = lock.load().unwrap();
+        assert!(seen == 0 || seen == 1);
+        writer.join().unwrap();
+        assert_eq!(lock.load(), Ok(1));
+    });
+}
+
+#[test]
+fn upgrade_is_exclusive() {
+    loom::model(|| {
+        let lock = pair();
+        let upgrader = |lock: Arc<OptimisticLockCoupling<Pair>>| {
+            move || {
+                let r = unsafe { lock.read() };
+                match r.and_then(|r| r.upgrade()) {
+                    Ok(w) => {
+                        // not an atomic increment, a second writer would lose an update
+                        let n = w.0.load(Relaxed);
+                        w.0.store(n + 1, Relaxed);
+                        1
+                    }
+                    Err(_) => 0,
+                }
+            }
+        };
+        let t1 = thread::spawn(upgrader(lock.clone()));
+        let t2 = thread::spawn(upgrader(lock.clone()));
+        let upgraded = t1.join().unwrap() + t2.join().unwrap();
+        assert!(upgraded >= 1);
+        assert_eq!(lock.read_with(|p| p.0.load(Relaxed)), Ok(upgraded));
+    });
+}
+
+#[test]
+fn outdate_is_never_missed() {
+    loom::model(|| {
+        let lock = pair();
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                let w = lock.write().unwrap();
+                w.0.store(1, Relaxed);
+                w.1.store(1, Relaxed);
+                w.unlock_obsolete();
+            })
+        };
+        match lock.read_with(|p| (p.0.load(Relaxed), p.1.load(Relaxed))) {
+            Ok((a, b)) => assert_eq!(a, b),
+            Err(e) => assert_ne!(e, OptimisticLockCouplingErrorType::Poisoned),
+        }
+        writer.join().unwrap();
+        assert_eq!(
+            lock.read_with(|_| ()),
+            Err(OptimisticLockCouplingErrorType::Outdated)
+        );
+    });
+}
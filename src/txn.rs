@@ -0,0 +1,228 @@
+//! Serializable transactions over many locks, the way Silo does them.
+//!
+//! Reads go into a `ReadSet`, writes wait in a private buffer, nothing is locked
+//! while the transaction runs. `commit` locks the written records in address order,
+//! so two commits can't deadlock, validates every read, installs the buffered values
+//! and releases the records with a new version. A failed or dropped transaction
+//! never touched a record, the locks it took during `commit` are given back as they were.
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::Ordering::*;
+
+use crate::{
+    panicking, try_lock_word, unlock_word, Backoff, DefaultLockWord, ExponentialBackoff, LockWord,
+    OptimisticLockCoupling, OptimisticLockCouplingErrorType, OptimisticLockCouplingResult, ReadSet,
+};
+
+/// a value waiting for `commit`, its type is forgotten until it is installed
+trait PendingWrite<'a, W: LockWord> {
+    fn word(&self) -> &'a W::Atomic;
+    /// the buffered value, a `T` of the record `word` belongs to
+    fn value(&mut self) -> *mut ();
+    /// the lock must be held
+    unsafe fn install(self: Box<Self>);
+}
+struct Pending<'a, T, W: LockWord> {
+    lock: &'a OptimisticLockCoupling<T, W>,
+    value: T,
+}
+impl<'a, T, W: LockWord> PendingWrite<'a, W> for Pending<'a, T, W> {
+    #[inline(always)]
+    fn word(&self) -> &'a W::Atomic {
+        &self.lock.version_lock_outdate
+    }
+    #[inline(always)]
+    fn value(&mut self) -> *mut () {
+        &mut self.value as *mut T as *mut ()
+    }
+    #[inline(always)]
+    unsafe fn install(self: Box<Self>) {
+        *self.lock.data.get() = self.value;
+    }
+}
+
+/// the words `commit` holds, given back on drop if it didn't get to release them
+struct Locked<'a, W: LockWord> {
+    words: Vec<(&'a W::Atomic, W)>,
+}
+impl<W: LockWord> Drop for Locked<'_, W> {
+    fn drop(&mut self) {
+        for &(word, _) in &self.words {
+            if panicking() {
+                // a value was half installed, or a release panicked on overflow
+                unlock_word(word, W::POISON);
+            } else {
+                // nothing was installed, same version as before
+                // `SeqCst` for `unpark_all`, like `unlock_word`
+                W::fetch_and(word, !W::LOCK, SeqCst);
+                #[cfg(feature = "std")]
+                crate::parking::unpark_all(word as *const W::Atomic as usize);
+            }
+        }
+    }
+}
+
+/// An optimistic transaction over records that may all hold different `T`s
+/// see `Transaction::run`, or build one by hand and `commit` it
+pub struct Transaction<'a, W: LockWord = DefaultLockWord> {
+    reads: ReadSet<'a, W>,
+    writes: Vec<Box<dyn PendingWrite<'a, W> + 'a>>,
+}
+impl<W: LockWord> Default for Transaction<'_, W> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            reads: ReadSet::new(),
+            writes: Vec::new(),
+        }
+    }
+}
+impl<'a, W: LockWord> Transaction<'a, W> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the buffered value of `lock`, if this transaction wrote it
+    #[inline(always)]
+    fn buffered<T>(&mut self, lock: &'a OptimisticLockCoupling<T, W>) -> Option<&mut T> {
+        let word = &lock.version_lock_outdate as *const W::Atomic;
+        self.writes
+            .iter_mut()
+            .find(|w| core::ptr::eq(w.word(), word))
+            // SAFETY: one lock word belongs to one record, the value is its `T`
+            .map(|w| unsafe { &mut *(w.value() as *mut T) })
+    }
+    /// read a record, the version goes into the read set
+    /// sees the buffered value if this transaction wrote the record before
+    #[inline(always)]
+    pub fn read<T, F, R>(
+        &mut self,
+        lock: &'a OptimisticLockCoupling<T, W>,
+        logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        match self.buffered(lock) {
+            Some(value) => Ok(logic(value)),
+            None => self.reads.read(lock, logic),
+        }
+    }
+    /// buffer a new value for a record, nothing is visible before `commit`
+    #[inline(always)]
+    pub fn write<T: 'a>(&mut self, lock: &'a OptimisticLockCoupling<T, W>, value: T) {
+        match self.buffered(lock) {
+            Some(buffered) => *buffered = value,
+            None => self.writes.push(Box::new(Pending { lock, value })),
+        }
+    }
+    /// read a record and buffer a changed copy of it
+    #[inline(always)]
+    pub fn update<T, F, R>(
+        &mut self,
+        lock: &'a OptimisticLockCoupling<T, W>,
+        logic: F,
+    ) -> OptimisticLockCouplingResult<R>
+    where
+        T: Clone + 'a,
+        F: FnOnce(&mut T) -> R,
+    {
+        if let Some(value) = self.buffered(lock) {
+            return Ok(logic(value));
+        }
+        let mut value = self.reads.read(lock, T::clone)?;
+        let r = logic(&mut value);
+        self.writes.push(Box::new(Pending { lock, value }));
+        Ok(r)
+    }
+    /// lock the write set, validate the read set, install the writes
+    /// fails without a trace if any record read changed, `Blocked` and `VersionUpdated` are worth a retry
+    pub fn commit(mut self) -> OptimisticLockCouplingResult<()> {
+        use OptimisticLockCouplingErrorType::*;
+        // one global order, two commits never wait on each other in a circle
+        self.writes
+            .sort_unstable_by_key(|w| w.word() as *const W::Atomic as usize);
+        let mut locked = Locked {
+            words: Vec::with_capacity(self.writes.len()),
+        };
+        for write in &self.writes {
+            let word = write.word();
+            let mut backoff = ExponentialBackoff::default();
+            loop {
+                match try_lock_word::<W>(word) {
+                    Ok(version) => {
+                        if W::compare_exchange(word, version, version + W::LOCK, Acquire, Relaxed)
+                            .is_ok()
+                        {
+                            // pairs with the fence in `validate`, like `lock_version`
+                            crate::sync::fence(Release);
+                            locked.words.push((word, version));
+                            break;
+                        }
+                    }
+                    // a writer, or a commit further along the same order, neither waits on us
+                    Err(Blocked) => {}
+                    Err(e) => return Err(e),
+                }
+                backoff.backoff();
+            }
+        }
+        crate::sync::fence(Acquire);
+        for &(word, version) in &self.reads.entries {
+            let current = match locked
+                .words
+                .binary_search_by_key(&(word as *const W::Atomic as usize), |(w, _)| {
+                    *w as *const W::Atomic as usize
+                }) {
+                // ours, compare with the version it was locked at
+                Ok(i) => locked.words[i].1,
+                Err(_) => try_lock_word::<W>(word)?,
+            };
+            if current != version {
+                return Err(VersionUpdated);
+            }
+        }
+        for write in self.writes.drain(..) {
+            // SAFETY: every record in the write set is locked
+            unsafe { write.install() };
+        }
+        // one at a time, if a release panics on overflow `Locked` still holds the rest
+        while let Some((word, _)) = locked.words.pop() {
+            unlock_word(word, W::ZERO);
+        }
+        Ok(())
+    }
+    /// run `logic` on a fresh transaction and commit it, until it commits
+    /// only `Poisoned`, `Outdated` and `TimedOut` are returned
+    /// waits with the default `ExponentialBackoff` between retries
+    #[inline(always)]
+    pub fn run<F, R>(logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        F: FnMut(&mut Self) -> OptimisticLockCouplingResult<R>,
+    {
+        Self::run_with(ExponentialBackoff::default(), logic)
+    }
+    /// `run` with your own `Backoff` between retries
+    pub fn run_with<B, F, R>(mut backoff: B, mut logic: F) -> OptimisticLockCouplingResult<R>
+    where
+        B: Backoff,
+        F: FnMut(&mut Self) -> OptimisticLockCouplingResult<R>,
+    {
+        use OptimisticLockCouplingErrorType::*;
+        backoff.reset();
+        'txn: loop {
+            let mut txn = Self::new();
+            match logic(&mut txn).and_then(|r| txn.commit().map(|_| r)) {
+                Ok(r) => {
+                    return Ok(r);
+                }
+                Err(e @ (Poisoned | Outdated | TimedOut)) => {
+                    return Err(e);
+                }
+                Err(_) => {
+                    backoff.backoff();
+                    continue 'txn;
+                }
+            }
+        }
+    }
+}
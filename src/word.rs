@@ -0,0 +1,138 @@
+//! The word a lock keeps all of its state in.
+use core::{
+    fmt::{Debug, Display},
+    ops::{Add, BitAnd, BitOr, Not, Shr},
+    sync::atomic::Ordering,
+};
+
+use crate::sync::AtomicU32;
+#[cfg(target_has_atomic = "64")]
+use crate::sync::AtomicU64;
+
+/// The integer behind the lock word, `u64` or `u32`
+///
+/// from the top: 1 bit poison | 1 bit overflow policy | version | 1 bit lock | 1 bit outdate
+///
+/// `u64` is the default wherever the target has 64 bit atomics, `u32` is the only word where it doesn't.
+/// Targets without a 32 bit compare-and-swap, like thumbv6m, aren't supported.
+/// `u32` halves the lock for memory-dense nodes,
+/// an `OptimisticLockCoupling<u32, u32>` is 8 bytes where the `u64` one is 16.
+/// The price is paid in version bits: 60 of them never run out,
+/// 28 of them are only 268 million writes, a hot lock gets there in minutes.
+/// A version that wrapped around would let a reader that slept through exactly 2^28 writes
+/// validate a torn read, so it never wraps, the lock does its `VersionOverflowPolicy` instead.
+/// Pick `u32` for nodes that get replaced long before that, like most tree nodes,
+/// and leave long-lived hot locks on `u64`.
+pub trait LockWord: sealed::Sealed {}
+
+pub(crate) mod sealed {
+    use super::*;
+
+    /// everything the lock does with its word, kept out of the public api
+    pub trait Sealed:
+        Copy
+        + Eq
+        + Debug
+        + Display
+        + Send
+        + Sync
+        + 'static
+        + Add<Output = Self>
+        + BitAnd<Output = Self>
+        + BitOr<Output = Self>
+        + Not<Output = Self>
+        + Shr<u32, Output = Self>
+    {
+        type Atomic: Send + Sync;
+        const ZERO: Self;
+        const OUTDATE: Self;
+        const LOCK: Self;
+        const OUTDATE_ON_OVERFLOW: Self;
+        const POISON: Self;
+        /// the version lives between the policy bit and the lock bit
+        const MAX_VERSION: Self;
+        fn new_atomic(word: Self) -> Self::Atomic;
+        fn load(atomic: &Self::Atomic, order: Ordering) -> Self;
+        fn compare_exchange(
+            atomic: &Self::Atomic,
+            current: Self,
+            new: Self,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<Self, Self>;
+        fn fetch_add(atomic: &Self::Atomic, val: Self, order: Ordering) -> Self;
+        fn fetch_or(atomic: &Self::Atomic, val: Self, order: Ordering) -> Self;
+        fn fetch_and(atomic: &Self::Atomic, val: Self, order: Ordering) -> Self;
+        fn fetch_update(
+            atomic: &Self::Atomic,
+            set_order: Ordering,
+            fetch_order: Ordering,
+            f: impl FnMut(Self) -> Option<Self>,
+        ) -> Result<Self, Self>;
+    }
+}
+
+macro_rules! lock_word {
+    ($word:ty, $atomic:ty) => {
+        impl sealed::Sealed for $word {
+            type Atomic = $atomic;
+            const ZERO: Self = 0;
+            const OUTDATE: Self = 0b1;
+            const LOCK: Self = 0b10;
+            const OUTDATE_ON_OVERFLOW: Self = 1 << (<$word>::BITS - 2);
+            const POISON: Self = 1 << (<$word>::BITS - 1);
+            const MAX_VERSION: Self = !(Self::POISON | Self::OUTDATE_ON_OVERFLOW) >> 2;
+            #[inline(always)]
+            fn new_atomic(word: Self) -> $atomic {
+                <$atomic>::new(word)
+            }
+            #[inline(always)]
+            fn load(atomic: &$atomic, order: Ordering) -> Self {
+                atomic.load(order)
+            }
+            #[inline(always)]
+            fn compare_exchange(
+                atomic: &$atomic,
+                current: Self,
+                new: Self,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<Self, Self> {
+                atomic.compare_exchange(current, new, success, failure)
+            }
+            #[inline(always)]
+            fn fetch_add(atomic: &$atomic, val: Self, order: Ordering) -> Self {
+                atomic.fetch_add(val, order)
+            }
+            #[inline(always)]
+            fn fetch_or(atomic: &$atomic, val: Self, order: Ordering) -> Self {
+                atomic.fetch_or(val, order)
+            }
+            #[inline(always)]
+            fn fetch_and(atomic: &$atomic, val: Self, order: Ordering) -> Self {
+                atomic.fetch_and(val, order)
+            }
+            #[inline(always)]
+            fn fetch_update(
+                atomic: &$atomic,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: impl FnMut(Self) -> Option<Self>,
+            ) -> Result<Self, Self> {
+                atomic.fetch_update(set_order, fetch_order, f)
+            }
+        }
+        impl LockWord for $word {}
+    };
+}
+
+#[cfg(target_has_atomic = "64")]
+lock_word!(u64, AtomicU64);
+lock_word!(u32, AtomicU32);
+
+/// The word a lock gets when none is named, see `LockWord`
+#[cfg(target_has_atomic = "64")]
+pub type DefaultLockWord = u64;
+/// The word a lock gets when none is named, see `LockWord`
+#[cfg(not(target_has_atomic = "64"))]
+pub type DefaultLockWord = u32;